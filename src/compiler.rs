@@ -1,23 +1,6 @@
-use std::convert::TryFrom;
-use crate::parse::{Document, Block, List, ListToken, TokenEnumerator, TokenWrapper};
-use numerals::roman::Roman;
-
-static ALPHABET_UPPER: [char; 26] = [
-    'A', 'B', 'C', 'D', 'E', 
-    'B', 'G', 'H', 'I', 'J', 
-    'K', 'L', 'M', 'N', 'O',
-    'P', 'Q', 'R', 'S', 'T', 
-    'U', 'V', 'W', 'X', 'Y', 
-    'Z',
-];
-static ALPHABET_LOWER: [char; 26] = [
-    'a', 'b', 'c', 'd', 'e', 
-    'f', 'g', 'h', 'i', 'j', 
-    'k', 'l', 'm', 'n', 'o',
-    'p', 'q', 'r', 's', 't', 
-    'u', 'v', 'w', 'x', 'y', 
-    'z',
-];
+use crate::parse::{Document, Metadata, Block, Inline, List, TokenEnumerator, Alignment, Row, Numbering, OutlineEntry, plain_text};
+use crate::template::Template;
+use unicode_width::UnicodeWidthStr;
 
 // made this a trait just for more extensibility
 // in the future
@@ -26,43 +9,229 @@ pub trait Compiler {
     fn compile(&mut self, document: &Document) -> String; 
 }
 
+/// How a document is laid out in LaTeX.
+#[derive(Debug, Clone)]
+pub struct LatexConfig {
+    /// The document class, used when the front matter doesn't set one.
+    pub class: String,
+    /// The sectioning commands (without the backslash) for each heading 
+    /// level, starting with `#`. If empty, they are chosen after the class.
+    pub sections: Vec<String>,
+    /// The document the compiled content is put into.
+    pub template: Template,
+}
+impl Default for LatexConfig {
+    fn default() -> Self {
+        LatexConfig {
+            class: String::from("article"),
+            sections: Vec::new(),
+            template: Template::default(),
+        }
+    }
+}
+impl LatexConfig {
+    /// The sectioning commands available in a document class.
+    pub fn sections_for(class: &str) -> Vec<String> {
+        let mut sections = vec!["section", "subsection", "subsubsection", "paragraph", "subparagraph"];
+
+        // only these classes have chapters
+        if let "book" | "report" | "memoir" | "scrbook" | "scrreprt" = class {
+            sections.insert(0, "chapter");
+        }
+        sections.into_iter().map(String::from).collect()
+    }
+}
+
 /// Compiles into LaTeX.
-pub struct IntoLatex;
+#[derive(Debug, Clone, Default)]
+pub struct IntoLatex {
+    pub config: LatexConfig,
+}
 
 impl IntoLatex {
+    pub fn new(config: LatexConfig) -> Self {
+        IntoLatex { config }
+    }
+    fn class<'a>(&'a self, metadata: &'a Metadata) -> &'a str {
+        metadata.class.as_deref().unwrap_or(&self.config.class)
+    }
+    /// The sectioning command of a heading level. Levels deeper than the 
+    /// last command are clamped to it.
+    fn section(sections: &[String], level: usize) -> &str {
+        match sections.get(level.saturating_sub(1)).or_else(|| sections.last()) {
+            Some(section) => section,
+            None => "section",
+        }
+    }
+    /// Escapes text so that every character is typeset as written.
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '%' | '&' | '$' | '#' | '_' | '{' | '}' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                '~' => escaped.push_str(r#"\textasciitilde{}"#),
+                '^' => escaped.push_str(r#"\textasciicircum{}"#),
+                '\\' => escaped.push_str(r#"\textbackslash{}"#),
+                '<' => escaped.push_str(r#"\textless{}"#),
+                '>' => escaped.push_str(r#"\textgreater{}"#),
+                '|' => escaped.push_str(r#"\textbar{}"#),
+                // breaks the `--` and `---` dash ligatures
+                '-' if chars.peek() == Some(&'-') => escaped.push_str("-{}"),
+                _ => escaped.push(c),
+            }
+        }
+        escaped
+    }
     fn push_text(buf: &mut String, text: &str) {
-        buf.push_str(text);
+        buf.push_str(&IntoLatex::escape(text));
+    }
+    // commands such as `\item` take an optional `[...]` argument, so the
+    // text printed after them from `start` can't begin with a bracket
+    fn protect_bracket(buf: &mut String, start: usize) {
+        if buf[start..].starts_with('[') {
+            buf.replace_range(start..start + 1, "{[}");
+        }
     }
-    fn print_paragraph(buf: &mut String, paragraph: &str) {
-        IntoLatex::push_text(buf, paragraph);
+    // hyperref reads URLs as they are, except for these characters
+    fn escape_url(url: &str) -> String {
+        let mut escaped = String::with_capacity(url.len());
+
+        for c in url.chars() {
+            if let '%' | '#' | '\\' | '{' | '}' = c {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+    fn push_command(buf: &mut String, command: &str, inlines: &[Inline]) {
+        buf.push_str(command);
+        buf.push('{');
+        IntoLatex::push_inlines(buf, inlines);
+        buf.push('}');
+    }
+    fn push_inlines(buf: &mut String, inlines: &[Inline]) {
+        for inline in inlines {
+            match inline {
+                Inline::Text(t) => IntoLatex::push_text(buf, t),
+                Inline::Emphasis(i) => IntoLatex::push_command(buf, r#"\emph"#, i),
+                Inline::Strong(i) => IntoLatex::push_command(buf, r#"\textbf"#, i),
+                Inline::Code(c) => {
+                    buf.push_str(r#"\texttt{"#);
+                    IntoLatex::push_text(buf, c);
+                    buf.push('}');
+                }
+                Inline::Math(m) => {
+                    buf.push_str(r#"\("#);
+                    buf.push_str(m);
+                    buf.push_str(r#"\)"#);
+                }
+                Inline::Link(text, url) => {
+                    buf.push_str(r#"\href{"#);
+                    buf.push_str(&IntoLatex::escape_url(url));
+                    buf.push_str("}{");
+                    IntoLatex::push_inlines(buf, text);
+                    buf.push('}');
+                }
+                Inline::Url(url) => {
+                    buf.push_str(r#"\url{"#);
+                    buf.push_str(&IntoLatex::escape_url(url));
+                    buf.push('}');
+                }
+                Inline::FootnoteReference(id) => IntoLatex::push_text(buf, &format!("[^{}]", id)),
+                Inline::Footnote(_, text) => IntoLatex::push_command(buf, r#"\footnote"#, text),
+                Inline::Reference(label, _) => {
+                    buf.push_str(r#"\ref{"#);
+                    buf.push_str(label);
+                    buf.push('}');
+                }
+                Inline::Raw(format, content) if format == "latex" => buf.push_str(content),
+                Inline::Raw(..) => (),
+            }
+        }
+    }
+    fn print_paragraph(buf: &mut String, paragraph: &[Inline]) {
+        IntoLatex::push_inlines(buf, paragraph);
         buf.push_str("\n");
     }
-    fn print_list(buf: &mut String, list: &List) {
+    fn print_code(buf: &mut String, code: &str) {
+        // `verbatim` ends at the first `\end{verbatim}`, even inside a line,
+        // while fancyvrb's `Verbatim` only ends at a line of its own
+        let environment = match code.contains(r#"\end{verbatim}"#) {
+            true => "Verbatim",
+            false => "verbatim",
+        };
+        buf.push_str(&format!(r#"\begin{{{}}}"#, environment));
+        buf.push('\n');
+        buf.push_str(code);
+        buf.push('\n');
+        buf.push_str(&format!(r#"\end{{{}}}"#, environment));
+        buf.push('\n');
+    }
+    fn print_list(&self, buf: &mut String, sections: &[String], list: &List) {
         let environment = match list.token.enumerator {
             Some(_) => "enumerate",
             None => "itemize",
         };
+        let wrapper = list.token.wrapper.map_text(IntoLatex::escape);
         let label = match &list.token.enumerator {
-            None => list.token.wrapper.unnumbered().clone(),
-            Some(e) => list.token.wrapper.label(&e.latex()),
+            None => wrapper.unnumbered(),
+            Some(e) => wrapper.label(&e.latex()),
         };
+        // LaTeX's letters stop at `z`, so longer lists are given their
+        // labels one by one
+        let explicit = matches!(list.token.enumerator, Some(TokenEnumerator::Alphabetical(_)))
+            && list.start + list.vec.len() - 1 > 26;
+
         buf.push_str(r#"\begin{"#);
         buf.push_str(environment);
         buf.push_str("}");
 
-        buf.push_str("[label=");
-        buf.push_str(&label);
-        buf.push_str("]");
+        if !explicit {
+            // braced so that enumitem doesn't parse the label's characters
+            buf.push_str("[label={");
+            buf.push_str(&label);
+            buf.push('}');
 
+            if list.token.enumerator.is_some() && list.start != 1 {
+                buf.push_str(&format!(", start={}", list.start));
+            }
+            buf.push(']');
+        }
         buf.push('\n');
 
-        for item in &list.vec { 
-            buf.push_str(r#"\item "#);
-            IntoLatex::push_text(buf, &item.text);
-            buf.push('\n');
+        for (index, item) in list.vec.iter().enumerate() { 
+            buf.push_str(r#"\item"#);
+
+            if let (true, Some(e)) = (explicit, &list.token.enumerator) {
+                buf.push_str("[{");
+                buf.push_str(&wrapper.label(&e.format(list.start + index)));
+                buf.push_str("}]");
+            }
+            buf.push(' ');
 
-            if let Some(list) = &item.list {
-                IntoLatex::print_list(buf, &list);
+            if item.blocks.is_empty() {
+                buf.push('\n');
+            }
+            for (i, block) in item.blocks.iter().enumerate() {
+                match &**block {
+                    // sublists are kept right below the text of the item
+                    Block::List(list) => self.print_list(buf, sections, list),
+                    Block::Paragraph(p) if i == 0 => {
+                        let start = buf.len();
+                        IntoLatex::print_paragraph(buf, p);
+                        IntoLatex::protect_bracket(buf, start);
+                    }
+                    part => {
+                        buf.push('\n');
+                        self.print_block(buf, sections, part);
+                    }
+                }
             }
         }
 
@@ -71,137 +240,1069 @@ impl IntoLatex {
         buf.push_str("}");
         buf.push('\n');
     }
-    fn print_block(buf: &mut String, part: &Block) {
+    // captions are needed to number a float, even if they are empty
+    fn print_caption(buf: &mut String, caption: &[Inline], label: &Option<String>) {
+        if !caption.is_empty() || label.is_some() {
+            IntoLatex::push_command(buf, r#"\caption"#, caption);
+            buf.push('\n');
+        }
+        if let Some(label) = label {
+            buf.push_str(r#"\label{"#);
+            buf.push_str(label);
+            buf.push_str("}\n");
+        }
+    }
+    fn print_table(buf: &mut String, header: &Row, align: &[Alignment], rows: &[Row], caption: &[Inline], label: &Option<String>) {
+        // tables that can be referenced are floats
+        let float = !caption.is_empty() || label.is_some();
+        let columns: String = align.iter()
+            .map(|a| match a {
+                Alignment::Center => 'c',
+                Alignment::Right => 'r',
+                _ => 'l',
+            })
+            .collect();
+        let print_row = |buf: &mut String, row: &Row| {
+            for (i, cell) in row.iter().enumerate() {
+                if i > 0 {
+                    buf.push_str(" & ");
+                }
+                let start = buf.len();
+                IntoLatex::push_inlines(buf, cell);
+
+                // the first cell comes after `\\` or a rule
+                if i == 0 {
+                    IntoLatex::protect_bracket(buf, start);
+                }
+            }
+            buf.push_str(" \\\\\n");
+        };
+        if float {
+            buf.push_str(r#"\begin{table}[htbp]"#);
+            buf.push('\n');
+            buf.push_str(r#"\centering"#);
+        } else {
+            buf.push_str(r#"\begin{center}"#);
+        }
+        buf.push('\n');
+        buf.push_str(r#"\begin{tabular}{"#);
+        buf.push_str(&columns);
+        buf.push_str("}\n");
+        buf.push_str(r#"\toprule"#);
+        buf.push('\n');
+        print_row(buf, header);
+        buf.push_str(r#"\midrule"#);
+        buf.push('\n');
+
+        for row in rows {
+            print_row(buf, row);
+        }
+        buf.push_str(r#"\bottomrule"#);
+        buf.push('\n');
+        buf.push_str(r#"\end{tabular}"#);
+        buf.push('\n');
+
+        if float {
+            IntoLatex::print_caption(buf, caption, label);
+            buf.push_str(r#"\end{table}"#);
+        } else {
+            buf.push_str(r#"\end{center}"#);
+        }
+        buf.push('\n');
+    }
+    // percentages are taken from the size of the page
+    fn length(value: &str, relative: &str) -> String {
+        match value.strip_suffix('%').and_then(|n| n.trim().parse::<f64>().ok()) {
+            Some(n) => format!("{}{}", n / 100.0, relative),
+            None => value.to_string(),
+        }
+    }
+    fn print_figure(buf: &mut String, path: &str, caption: &[Inline], label: &Option<String>, width: &Option<String>, height: &Option<String>) {
+        let mut options = Vec::new();
+
+        if let Some(width) = width {
+            options.push(format!("width={}", IntoLatex::length(width, r#"\linewidth"#)));
+        }
+        if let Some(height) = height {
+            options.push(format!("height={}", IntoLatex::length(height, r#"\textheight"#)));
+        }
+        if width.is_some() && height.is_some() {
+            options.push(String::from("keepaspectratio"));
+        }
+        buf.push_str(r#"\begin{figure}[htbp]"#);
+        buf.push('\n');
+        buf.push_str(r#"\centering"#);
+        buf.push('\n');
+        buf.push_str(r#"\includegraphics"#);
+
+        if !options.is_empty() {
+            buf.push('[');
+            buf.push_str(&options.join(", "));
+            buf.push(']');
+        }
+        buf.push('{');
+        buf.push_str(path);
+        buf.push_str("}\n");
+
+        IntoLatex::print_caption(buf, caption, label);
+        buf.push_str(r#"\end{figure}"#);
+        buf.push('\n');
+    }
+    // babel uses language names instead of codes, other languages are
+    // left to the default of LaTeX
+    fn babel_language(language: &str) -> Option<&'static str> {
+        let code = language.split(['-', '_']).next().unwrap_or(language);
+
+        let name = match code.to_lowercase().as_str() {
+            "en" => "english",
+            "es" => "spanish",
+            "fr" => "french",
+            "de" => "ngerman",
+            "it" => "italian",
+            "pt" => "portuguese",
+            "nl" => "dutch",
+            "ca" => "catalan",
+            "sv" => "swedish",
+            "da" => "danish",
+            "no" | "nb" => "norsk",
+            "fi" => "finnish",
+            "pl" => "polish",
+            "cs" => "czech",
+            "ru" => "russian",
+            "el" => "greek",
+            "tr" => "turkish",
+            _ => return None,
+        };
+        Some(name)
+    }
+    fn print_packages(buf: &mut String) {
+        buf.push_str(r#"\usepackage{enumitem}"#);
+        buf.push('\n');
+        buf.push_str(r#"\usepackage{booktabs}"#);
+        buf.push('\n');
+        buf.push_str(r#"\usepackage{graphicx}"#);
+        buf.push('\n');
+        buf.push_str(r#"\usepackage{fancyvrb}"#);
+        buf.push('\n');
+        buf.push_str(r#"\usepackage{hyperref}"#);
+    }
+    fn print_language(buf: &mut String, metadata: &Metadata) {
+        if let Some(language) = metadata.language.as_deref().and_then(IntoLatex::babel_language) {
+            buf.push_str(r#"\usepackage["#);
+            buf.push_str(language);
+            buf.push_str(r#"]{babel}"#);
+        }
+    }
+    fn print_metadata(buf: &mut String, metadata: &Metadata) {
+        // the PDF metadata
+        let info = [
+            ("pdftitle", &metadata.title),
+            ("pdfauthor", &metadata.author),
+            ("pdflang", &metadata.language),
+        ];
+        let info: Vec<String> = info.iter()
+            .filter_map(|(key, value)| value.as_ref().map(|v| {
+                format!("{}={{{}}}", key, IntoLatex::escape(v))
+            }))
+            .collect();
+
+        if !info.is_empty() {
+            buf.push_str(r#"\hypersetup{"#);
+            buf.push_str(&info.join(", "));
+            buf.push_str("}\n");
+        }
+
+        if let Some(title) = &metadata.title {
+            let fields = [
+                (r#"\title{"#, Some(title)),
+                (r#"\author{"#, metadata.author.as_ref()),
+                (r#"\date{"#, metadata.date.as_ref()),
+            ];
+            for (command, value) in fields.iter() {
+                buf.push_str(command);
+                // an empty date hides the date LaTeX would put by default
+                if let Some(value) = value {
+                    IntoLatex::push_text(buf, value);
+                }
+                buf.push_str("}\n");
+            }
+        }
+        buf.truncate(buf.trim_end().len());
+    }
+    fn print_body(&self, buf: &mut String, document: &Document) {
+        let metadata = &document.metadata;
+        // books don't have an abstract
+        let abstract_environment = match self.class(metadata) {
+            "article" | "report" | "amsart" | "scrartcl" | "scrreprt" | "memoir" => "abstract",
+            _ => "quote",
+        };
+
+        if metadata.title.is_some() {
+            buf.push_str(r#"\maketitle"#);
+            buf.push('\n');
+        }
+        if let Some(a) = &metadata.r#abstract {
+            buf.push_str(&format!(r#"\begin{{{}}}"#, abstract_environment));
+            buf.push('\n');
+            IntoLatex::push_text(buf, a);
+            buf.push('\n');
+            buf.push_str(&format!(r#"\end{{{}}}"#, abstract_environment));
+            buf.push('\n');
+        }
+        if metadata.toc {
+            buf.push_str(r#"\tableofcontents"#);
+            buf.push('\n');
+        }
+        buf.push('\n');
+
+        let sections = match self.config.sections.is_empty() {
+            true => LatexConfig::sections_for(self.class(metadata)),
+            false => self.config.sections.clone(),
+        };
+        for block in &document.blocks {
+            self.print_block(buf, &sections, block);
+        }
+        buf.truncate(buf.trim_end().len());
+    }
+    fn print_block(&self, buf: &mut String, sections: &[String], part: &Block) {
         match part {
-            Block::Heading(level, title) => { 
-                let latex_heading = match level {
-                    // FIXME: hmm, shouldn't section be a better 
-                    // fit for level 0 instead of chapter?
-                    0 => r#"\chapter{"#, 
-                    1 => r#"\section{"#,
-                    2 => r#"\subsection{"#,
-                    3 => r#"\subsubsection{"#,
-                    4 => r#"\paragraph{"#,
-                    5 => r#"\subparagraph{"#,
-                    _ => r#"\heading{"#,
-                };
-                buf.push_str(latex_heading);
-                buf.push_str(&title);
+            Block::Heading(level, title, label) => { 
+                buf.push('\\');
+                buf.push_str(IntoLatex::section(sections, *level));
+                buf.push('{');
+                IntoLatex::push_inlines(buf, title);
                 buf.push_str("}\n");
+
+                if let Some(label) = label {
+                    buf.push_str(r#"\label{"#);
+                    buf.push_str(label);
+                    buf.push_str("}\n");
+                }
             },
             Block::Paragraph(p) => IntoLatex::print_paragraph(buf, &p),
-            Block::List(list) => IntoLatex::print_list(buf, &list),
+            Block::List(list) => self.print_list(buf, sections, list),
+            Block::CodeBlock { code, .. } => IntoLatex::print_code(buf, code),
+            Block::Math(m) => {
+                buf.push_str(r#"\["#);
+                buf.push('\n');
+                buf.push_str(m);
+                buf.push('\n');
+                buf.push_str(r#"\]"#);
+                buf.push('\n');
+            }
+            Block::Quote(blocks) => {
+                // `quotation` indents the paragraphs of longer quotes
+                let environment = match blocks.len() {
+                    1 => "quote",
+                    _ => "quotation",
+                };
+                buf.push_str(r#"\begin{"#);
+                buf.push_str(environment);
+                buf.push_str("}\n");
+
+                for block in blocks {
+                    self.print_block(buf, sections, block);
+                }
+                buf.push_str(r#"\end{"#);
+                buf.push_str(environment);
+                buf.push_str("}\n");
+            }
+            Block::Table { header, align, rows, caption, label } => 
+                IntoLatex::print_table(buf, header, align, rows, caption, label),
+            Block::Figure { path, caption, label, width, height } => 
+                IntoLatex::print_figure(buf, path, caption, label, width, height),
+            // footnotes are printed where they are referenced
+            Block::FootnoteDefinition(..) => return,
+            Block::Raw { format, content } if format == "latex" => {
+                buf.push_str(content);
+                buf.push('\n');
+            }
+            Block::Raw { .. } => return,
+            Block::TableOfContents => {
+                buf.push_str(r#"\tableofcontents"#);
+                buf.push('\n');
+            }
         }
         buf.push_str("\n");
     }
 }
 impl Compiler for IntoLatex {
     fn compile(&mut self, document: &Document) -> String {
-        let mut string = String::new();
+        let metadata = &document.metadata;
+        let text = |value: &Option<String>| value.as_deref()
+            .map(IntoLatex::escape)
+            .unwrap_or_default();
 
-        string.push_str(
-r#"\documentclass{article}
+        self.config.template.render(|name| {
+            let mut buf = String::new();
 
-\usepackage{enumitem}
+            match name {
+                "body" => self.print_body(&mut buf, document),
+                "class" => buf.push_str(self.class(metadata)),
+                "packages" => IntoLatex::print_packages(&mut buf),
+                "language" => IntoLatex::print_language(&mut buf, metadata),
+                "metadata" => IntoLatex::print_metadata(&mut buf, metadata),
+                "title" => buf.push_str(&text(&metadata.title)),
+                "author" => buf.push_str(&text(&metadata.author)),
+                "date" => buf.push_str(&text(&metadata.date)),
+                _ => (),
+            }
+            buf
+        })
+    }
+}
 
-\begin{document}
-"#);
+/// Compiles into a standalone HTML document.
+pub struct IntoHtml;
 
-        for block in &document.blocks {
-            IntoLatex::print_block(&mut string, &block);
+// what is kept track of while printing the blocks
+struct HtmlState {
+    /// The CSS `content` of every list label, the position of a label
+    /// in it is used as its class name.
+    labels: Vec<String>,
+    numbering: Numbering,
+}
+
+impl IntoHtml {
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+
+        for c in text.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                _ => escaped.push(c),
+            }
+        }
+        escaped
+    }
+    // escapes text to be put inside a quoted CSS string
+    fn escape_css(text: &str) -> String {
+        text.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+    fn push_element(buf: &mut String, tag: &str, inlines: &[Inline]) {
+        buf.push('<');
+        buf.push_str(tag);
+        buf.push('>');
+        IntoHtml::push_inlines(buf, inlines);
+        buf.push_str("</");
+        buf.push_str(tag);
+        buf.push('>');
+    }
+    fn push_inlines(buf: &mut String, inlines: &[Inline]) {
+        for inline in inlines {
+            match inline {
+                Inline::Text(t) => buf.push_str(&IntoHtml::escape(t)),
+                Inline::Emphasis(i) => IntoHtml::push_element(buf, "em", i),
+                Inline::Strong(i) => IntoHtml::push_element(buf, "strong", i),
+                Inline::Code(c) => {
+                    buf.push_str("<code>");
+                    buf.push_str(&IntoHtml::escape(c));
+                    buf.push_str("</code>");
+                }
+                Inline::Math(m) => {
+                    buf.push_str(r#"<span class="math inline">\("#);
+                    buf.push_str(&IntoHtml::escape(m));
+                    buf.push_str(r#"\)</span>"#);
+                }
+                Inline::Link(text, url) => {
+                    buf.push_str(&format!("<a href=\"{}\">", IntoHtml::escape(url)));
+                    IntoHtml::push_inlines(buf, text);
+                    buf.push_str("</a>");
+                }
+                Inline::Url(url) => {
+                    let url = IntoHtml::escape(url);
+                    buf.push_str(&format!("<a href=\"{0}\">{0}</a>", url));
+                }
+                Inline::FootnoteReference(id) => buf.push_str(&IntoHtml::escape(&format!("[^{}]", id))),
+                Inline::Footnote(number, _) => buf.push_str(&format!(
+                    "<sup class=\"footnote-ref\"><a href=\"#fn-{0}\">{0}</a></sup>", 
+                    number
+                )),
+                Inline::Reference(label, number) => buf.push_str(&format!(
+                    "<a href=\"#{}\">{}</a>",
+                    IntoHtml::escape(label),
+                    IntoHtml::escape(number.as_deref().unwrap_or(label))
+                )),
+                Inline::Raw(format, content) if format == "html" => buf.push_str(content),
+                Inline::Raw(..) => (),
+            }
+        }
+    }
+    fn print_code(buf: &mut String, lang: &Option<String>, code: &str) {
+        match lang {
+            Some(lang) => {
+                buf.push_str(r#"<pre><code class="language-"#);
+                buf.push_str(&IntoHtml::escape(lang));
+                buf.push_str(r#"">"#);
+            }
+            None => buf.push_str("<pre><code>"),
         }
+        buf.push_str(&IntoHtml::escape(code));
+        buf.push_str("</code></pre>\n");
+    }
+    fn print_list(buf: &mut String, state: &mut HtmlState, list: &List) {
+        let wrapper = list.token.wrapper.map_text(IntoHtml::escape_css);
+        let content = match &list.token.enumerator {
+            None => format!("\"{}\"", wrapper.unnumbered()),
+            Some(e) => {
+                let counter = format!("\" counter(aldoc-item, {}) \"", e.css());
+                format!("\"{}\"", wrapper.label(&counter))
+            }
+        };
+        let labels = &mut state.labels;
+        let class = match labels.iter().position(|l| *l == content) {
+            Some(i) => i,
+            None => {
+                labels.push(content);
+                labels.len() - 1
+            }
+        };
+        let element = match &list.token.enumerator {
+            Some(e) => {
+                buf.push_str(r#"<ol type=""#);
+                buf.push_str(e.html_type());
+                buf.push_str(r#"" "#);
+                "ol"
+            }
+            None => {
+                buf.push_str("<ul ");
+                "ul"
+            }
+        };
+        buf.push_str(&format!("class=\"aldoc-list aldoc-label-{}\"", class));
 
-        string.push_str(r#"\end{document}"#);
-        string
+        if list.token.enumerator.is_some() && list.start != 1 {
+            // the counter is incremented before each item is shown
+            buf.push_str(&format!(
+                " start=\"{}\" style=\"counter-reset: aldoc-item {}\"", 
+                list.start, 
+                list.start as isize - 1
+            ));
+        }
+        buf.push_str(">\n");
+
+        for item in &list.vec {
+            // items with a single paragraph don't wrap it in `<p>`
+            let loose = item.blocks.iter()
+                .filter(|b| !matches!(***b, Block::List(_)))
+                .count() > 1;
+
+            buf.push_str("<li>");
+
+            for block in &item.blocks {
+                match &**block {
+                    Block::Paragraph(p) if !loose => IntoHtml::push_inlines(buf, p),
+                    part => {
+                        if !buf.ends_with('\n') {
+                            buf.push('\n');
+                        }
+                        IntoHtml::print_block(buf, state, part);
+                    }
+                }
+            }
+            buf.push_str("</li>\n");
+        }
+
+        buf.push_str("</");
+        buf.push_str(element);
+        buf.push_str(">\n");
+    }
+    // the `id` attribute of an element that can be referenced
+    fn id(label: &Option<String>) -> String {
+        match label {
+            Some(label) => format!(" id=\"{}\"", IntoHtml::escape(label)),
+            None => String::new(),
+        }
+    }
+    // every heading gets an id for the table of contents, the ones made
+    // up start with `aldoc-` like the other names of the compiler
+    fn heading_id(label: &Option<String>, number: &str) -> String {
+        match label {
+            Some(label) => label.clone(),
+            None => format!("aldoc-section-{}", number),
+        }
+    }
+    fn print_table(buf: &mut String, header: &Row, align: &[Alignment], rows: &[Row], caption: &[Inline], label: &Option<String>) {
+        let print_row = |buf: &mut String, tag: &str, row: &Row| {
+            buf.push_str("<tr>");
+
+            for (cell, align) in row.iter().zip(align) {
+                let style = match align {
+                    Alignment::Default => "",
+                    Alignment::Left => r#" style="text-align: left""#,
+                    Alignment::Center => r#" style="text-align: center""#,
+                    Alignment::Right => r#" style="text-align: right""#,
+                };
+                buf.push_str(&format!("<{}{}>", tag, style));
+                IntoHtml::push_inlines(buf, cell);
+                buf.push_str(&format!("</{}>", tag));
+            }
+            buf.push_str("</tr>\n");
+        };
+        buf.push_str(&format!("<table{}>\n", IntoHtml::id(label)));
+
+        if !caption.is_empty() {
+            IntoHtml::push_element(buf, "caption", caption);
+            buf.push('\n');
+        }
+        buf.push_str("<thead>\n");
+        print_row(buf, "th", header);
+        buf.push_str("</thead>\n<tbody>\n");
+
+        for row in rows {
+            print_row(buf, "td", row);
+        }
+        buf.push_str("</tbody>\n</table>\n");
+    }
+    fn print_header(buf: &mut String, metadata: &Metadata) {
+        if metadata.title.is_none() {
+            return;
+        }
+        let fields = [
+            ("h1", "title", &metadata.title),
+            ("p", "author", &metadata.author),
+            ("p", "date", &metadata.date),
+        ];
+        buf.push_str("<header>\n");
+
+        for (tag, class, value) in fields.iter() {
+            if let Some(value) = value {
+                buf.push_str(&format!(
+                    "<{0} class=\"{1}\">{2}</{0}>\n", 
+                    tag, class, IntoHtml::escape(value)
+                ));
+            }
+        }
+        buf.push_str("</header>\n");
+
+        if let Some(a) = &metadata.r#abstract {
+            buf.push_str("<section class=\"abstract\">\n<p>");
+            buf.push_str(&IntoHtml::escape(a));
+            buf.push_str("</p>\n</section>\n");
+        }
+    }
+    fn print_block(buf: &mut String, state: &mut HtmlState, part: &Block) {
+        // numbered the same as the entries of the table of contents
+        let number = state.numbering.next(part);
+
+        match part {
+            Block::Heading(level, title, label) => {
+                let tag = format!("h{}", (*level).clamp(1, 6));
+                let id = IntoHtml::heading_id(label, &number.unwrap_or_default());
+                buf.push_str(&format!("<{} id=\"{}\">", tag, IntoHtml::escape(&id)));
+                IntoHtml::push_inlines(buf, title);
+                buf.push_str(&format!("</{}>\n", tag));
+            }
+            Block::Paragraph(p) => {
+                IntoHtml::push_element(buf, "p", p);
+                buf.push('\n');
+            }
+            Block::List(list) => IntoHtml::print_list(buf, state, list),
+            Block::CodeBlock { lang, code } => IntoHtml::print_code(buf, lang, code),
+            Block::Math(m) => {
+                buf.push_str(r#"<div class="math display">\["#);
+                buf.push_str(&IntoHtml::escape(m));
+                buf.push_str("\\]</div>\n");
+            }
+            Block::Quote(blocks) => {
+                buf.push_str("<blockquote>\n");
+
+                for block in blocks {
+                    IntoHtml::print_block(buf, state, block);
+                }
+                buf.push_str("</blockquote>\n");
+            }
+            Block::Table { header, align, rows, caption, label } => 
+                IntoHtml::print_table(buf, header, align, rows, caption, label),
+            Block::Figure { path, caption, label, width, height } => {
+                buf.push_str("<figure");
+                buf.push_str(&IntoHtml::id(label));
+                buf.push_str(&format!(
+                    "><img src=\"{}\" alt=\"{}\"",
+                    IntoHtml::escape(path),
+                    IntoHtml::escape(&plain_text(caption))
+                ));
+                let size: Vec<String> = [("width", width), ("height", height)].iter()
+                    .filter_map(|(key, value)| value.as_ref().map(|v| format!("{}: {}", key, v)))
+                    .collect();
+
+                if !size.is_empty() {
+                    buf.push_str(&format!(" style=\"{}\"", IntoHtml::escape(&size.join("; "))));
+                }
+                buf.push('>');
+
+                if !caption.is_empty() {
+                    IntoHtml::push_element(buf, "figcaption", caption);
+                }
+                buf.push_str("</figure>\n");
+            }
+            Block::FootnoteDefinition(..) => (),
+            // filled in once the whole document is known, the marker
+            // can't be confused with text since `<` is always escaped
+            Block::TableOfContents => buf.push_str(IntoHtml::CONTENTS),
+            Block::Raw { format, content } if format == "html" => {
+                buf.push_str(content);
+                buf.push('\n');
+            }
+            Block::Raw { .. } => (),
+        }
+    }
+    const CONTENTS: &'static str = "<!-- contents -->\n";
+
+    fn print_outline(buf: &mut String, entries: &[OutlineEntry]) {
+        buf.push_str("<ul>\n");
+
+        for entry in entries {
+            buf.push_str("<li>");
+
+            let id = IntoHtml::heading_id(&entry.label, &entry.number);
+            buf.push_str(&format!("<a href=\"#{}\">", IntoHtml::escape(&id)));
+            buf.push_str(&entry.number);
+            buf.push(' ');
+            IntoHtml::push_inlines(buf, &entry.title);
+            buf.push_str("</a>");
+
+            if !entry.children.is_empty() {
+                buf.push('\n');
+                IntoHtml::print_outline(buf, &entry.children);
+            }
+            buf.push_str("</li>\n");
+        }
+        buf.push_str("</ul>\n");
     }
 }
+impl Compiler for IntoHtml {
+    fn compile(&mut self, document: &Document) -> String {
+        let mut body = String::new();
+        let mut state = HtmlState {
+            labels: Vec::new(),
+            numbering: Numbering::default(),
+        };
+
+        let metadata = &document.metadata;
+
+        IntoHtml::print_header(&mut body, metadata);
+
+        if metadata.toc {
+            body.push_str(IntoHtml::CONTENTS);
+        }
+
+        for block in &document.blocks {
+            IntoHtml::print_block(&mut body, &mut state, block);
+        }
+        if !document.footnotes.is_empty() {
+            body.push_str("<section class=\"footnotes\">\n<ol>\n");
+
+            for (i, text) in document.footnotes.iter().enumerate() {
+                body.push_str(&format!("<li id=\"fn-{}\">", i + 1));
+                IntoHtml::push_inlines(&mut body, text);
+                body.push_str("</li>\n");
+            }
+            body.push_str("</ol>\n</section>\n");
+        }
+        if body.contains(IntoHtml::CONTENTS) {
+            let mut contents = String::from("<nav class=\"contents\">\n");
+            IntoHtml::print_outline(&mut contents, &document.outline());
+            contents.push_str("</nav>\n");
 
-fn roman(number: i16, uppercase: bool) -> String {
-    let roman = Roman::from(number);
+            body = body.replace(IntoHtml::CONTENTS, &contents);
+        }
+
+        let title = metadata.title.clone()
+            .or_else(|| document.blocks.iter()
+                .find_map(|b| match &**b {
+                    Block::Heading(_, title, _) => Some(plain_text(title)),
+                    _ => None,
+                })
+            )
+            .unwrap_or_default();
 
-    if uppercase {
-        format!("{:X}", roman)
-    } else {
-        format!("{:x}", roman)
+        let mut string = String::new();
+
+        string.push_str("<!DOCTYPE html>\n");
+        match &metadata.language {
+            Some(language) => string.push_str(&format!(
+                "<html lang=\"{}\">\n", 
+                IntoHtml::escape(language)
+            )),
+            None => string.push_str("<html>\n"),
+        }
+        string.push_str(
+r#"<head>
+<meta charset="utf-8">
+"#);
+        if let Some(author) = &metadata.author {
+            string.push_str(&format!(
+                "<meta name=\"author\" content=\"{}\">\n", 
+                IntoHtml::escape(author)
+            ));
+        }
+        string.push_str("<title>");
+        string.push_str(&IntoHtml::escape(&title));
+        string.push_str(
+r#"</title>
+<style>
+.aldoc-list { list-style: none; counter-reset: aldoc-item; }
+.aldoc-list > li { counter-increment: aldoc-item; }
+.aldoc-list > li::before { margin-right: 0.5em; }
+"#);
+        for (i, content) in state.labels.iter().enumerate() {
+            string.push_str(&format!(".aldoc-label-{} > li::before {{ content: {}; }}\n", i, content));
+        }
+        string.push_str(
+r#"</style>
+</head>
+<body>
+"#);
+        string.push_str(&body);
+        string.push_str(
+r#"</body>
+</html>"#);
+        string
     }
 }
+
 /// Compiles into terminal friendly text.
-pub struct IntoPrintable;
+#[derive(Debug, Clone)]
+pub struct IntoPrintable {
+    /// Whether the text is styled with escape codes, which should only
+    /// be done when it's shown in a terminal.
+    pub color: bool,
+}
+impl Default for IntoPrintable {
+    fn default() -> Self {
+        IntoPrintable { color: true }
+    }
+}
+
+// what is kept track of while printing the blocks
+struct PrintState {
+    numbering: Numbering,
+    /// Whether the headings show their numbers, which they only do when
+    /// they can be referenced or are listed in a table of contents.
+    numbered: bool,
+    /// The table of contents, already printed.
+    contents: String,
+}
 
 impl IntoPrintable {
-    fn print_paragraph(buf: &mut String, par: &str) {
-        buf.push_str(par);
+    pub fn new(color: bool) -> Self {
+        IntoPrintable { color }
+    }
+    // uses SGR escape codes, each span only turns off
+    // its own attribute so that they can be nested
+    fn push_styled(buf: &mut String, on: &str, off: &str, inlines: &[Inline]) {
+        buf.push_str(on);
+        IntoPrintable::push_inlines(buf, inlines);
+        buf.push_str(off);
+    }
+    fn push_inlines(buf: &mut String, inlines: &[Inline]) {
+        for inline in inlines {
+            match inline {
+                Inline::Text(t) => buf.push_str(t),
+                Inline::Emphasis(i) => IntoPrintable::push_styled(buf, "\x1b[3m", "\x1b[23m", i),
+                Inline::Strong(i) => IntoPrintable::push_styled(buf, "\x1b[1m", "\x1b[22m", i),
+                Inline::Code(c) => {
+                    buf.push_str("\x1b[7m");
+                    buf.push_str(c);
+                    buf.push_str("\x1b[27m");
+                }
+                Inline::Math(m) => {
+                    buf.push('$');
+                    buf.push_str(m);
+                    buf.push('$');
+                }
+                Inline::Link(text, url) => {
+                    IntoPrintable::push_inlines(buf, text);
+                    buf.push_str(" (");
+                    buf.push_str(url);
+                    buf.push(')');
+                }
+                Inline::Url(url) => IntoPrintable::push_styled(buf, "\x1b[4m", "\x1b[24m", &[Inline::Text(url.clone())]),
+                Inline::FootnoteReference(id) => buf.push_str(&format!("[^{}]", id)),
+                Inline::Footnote(number, _) => buf.push_str(&format!("[{}]", number)),
+                Inline::Reference(label, number) => match number {
+                    Some(number) => buf.push_str(number),
+                    None => buf.push_str(&format!("@{}", label)),
+                },
+                Inline::Raw(..) => (),
+            }
+        }
+    }
+    fn print_paragraph(buf: &mut String, par: &[Inline]) {
+        IntoPrintable::push_inlines(buf, par);
         buf.push_str("\n");
     }
-    fn print_list(buf: &mut String, list: &List, indent: usize) {
-        for _ in 0..indent {
-            buf.push_str("  ");
+    fn print_code(buf: &mut String, code: &str) {
+        for line in code.lines() {
+            if !line.is_empty() {
+                buf.push_str("    ");
+            }
+            buf.push_str(line);
+            buf.push('\n');
         }
-        // index starts on 1
-        let get_token = |i: i16| 
+    }
+    fn print_list(buf: &mut String, state: &mut PrintState, list: &List) {
+        // index starts on the first item's value
+        let get_token = |i: usize| 
             match &list.token.enumerator {
-                None => list.token.wrapper.unnumbered().clone(),
-                Some(e) => { 
-                    use TokenEnumerator::*;
-
-                    let enumerated = match &e {
-                        Numerical => format!("{}", i),
-                        Alphabetical(u) => {
-                            // FIXME: Should this happen?
-                            // What are alternatives to this?
-                            
-                            if i > 26 {
-                                panic!("List bigger than alphabet size");
-                            }
-                            if *u {
-                                format!("{}", ALPHABET_UPPER[(i - 1) as usize])
-                            } else {
-                                format!("{}", ALPHABET_LOWER[(i - 1) as usize])
-                            }
-                        }
-                        Roman(u) => format!("{}", roman(i, *u)),
-                    };
-                    list.token.wrapper.label(&enumerated)
-                }
+                None => list.token.wrapper.unnumbered(),
+                Some(e) => list.token.wrapper.label(&e.format(i)),
             };
 
-        let iter = list.vec.iter().enumerate();
-        for (index, item) in iter {
-            // limitation on list size
-            let index = i16::try_from(index).expect("List bigger than 32768 elements");
+        for (index, item) in list.vec.iter().enumerate() {
+            let mut content = String::new();
 
-            buf.push_str(&get_token(index + 1));
+            for (i, block) in item.blocks.iter().enumerate() {
+                // sublists are kept right below the text of the item
+                if i > 0 && !matches!(**block, Block::List(_)) {
+                    content.push('\n');
+                }
+                IntoPrintable::print_content(&mut content, state, block);
+            }
+            buf.push_str(&get_token(list.start + index));
             buf.push(' ');
-            buf.push_str(&item.text);
-            buf.push('\n');
 
-            if let Some(list) = &item.list {
-                IntoPrintable::print_list(buf, &list, indent + 1);
+            if content.is_empty() {
+                buf.push('\n');
+            }
+            // the content is indented below the token
+            for (i, line) in content.lines().enumerate() {
+                if i > 0 && !line.is_empty() {
+                    buf.push_str("  ");
+                }
+                buf.push_str(line);
+                buf.push('\n');
+            }
+        }
+    }
+    // the text without its escape codes
+    fn strip(text: &str) -> String {
+        let mut stripped = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find('\x1b') {
+            stripped.push_str(&rest[..start]);
+            rest = &rest[start..];
+            rest = match rest.find('m') {
+                Some(end) => &rest[end + 1..],
+                None => "",
+            };
+        }
+        stripped.push_str(rest);
+        stripped
+    }
+    // the columns the text takes on the terminal, without escape codes
+    fn width(text: &str) -> usize {
+        IntoPrintable::strip(text).width()
+    }
+    fn print_table(buf: &mut String, header: &Row, align: &[Alignment], rows: &[Row]) {
+        let render = |row: &Row| -> Vec<String> {
+            row.iter()
+                .map(|cell| {
+                    let mut text = String::new();
+                    IntoPrintable::push_inlines(&mut text, cell);
+                    text
+                })
+                .collect()
+        };
+        let header = render(header);
+        let rows: Vec<Vec<String>> = rows.iter().map(render).collect();
+
+        let widths: Vec<usize> = (0..align.len())
+            .map(|i| {
+                std::iter::once(&header)
+                    .chain(&rows)
+                    .map(|row| IntoPrintable::width(&row[i]))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let print_rule = |buf: &mut String, left: char, middle: char, right: char| {
+            buf.push(left);
+
+            for (i, width) in widths.iter().enumerate() {
+                if i > 0 {
+                    buf.push(middle);
+                }
+                buf.push_str(&"\u{2500}".repeat(width + 2));
+            }
+            buf.push(right);
+            buf.push('\n');
+        };
+        let print_row = |buf: &mut String, row: &[String]| {
+            for ((cell, width), align) in row.iter().zip(&widths).zip(align) {
+                let space = width - IntoPrintable::width(cell);
+                let before = match align {
+                    Alignment::Center => space / 2,
+                    Alignment::Right => space,
+                    _ => 0,
+                };
+                buf.push_str("\u{2502} ");
+                buf.push_str(&" ".repeat(before));
+                buf.push_str(cell);
+                buf.push_str(&" ".repeat(space - before + 1));
             }
+            buf.push_str("\u{2502}\n");
+        };
+        print_rule(buf, '\u{250c}', '\u{252c}', '\u{2510}');
+        print_row(buf, &header);
+        print_rule(buf, '\u{251c}', '\u{253c}', '\u{2524}');
+
+        for row in &rows {
+            print_row(buf, row);
         }
+        print_rule(buf, '\u{2514}', '\u{2534}', '\u{2518}');
+    }
+    fn print_block(buf: &mut String, state: &mut PrintState, part: &Block) {
+        IntoPrintable::print_content(buf, state, part);
+        buf.push('\n');
     }
-    fn print_block(buf: &mut String, part: &Block) {
+    fn print_content(buf: &mut String, state: &mut PrintState, part: &Block) {
+        // numbered the same as the references to them
+        let number = state.numbering.next(part);
+
         match part {
-            Block::Heading(level, title) => { 
+            Block::Heading(level, title, _) => { 
                 for _ in 0..*level {
                     buf.push('#');
                 }
                 buf.push(' ');
-                buf.push_str(&title);
+
+                if let (true, Some(number)) = (state.numbered, number) {
+                    buf.push_str(&number);
+                    buf.push(' ');
+                }
+                IntoPrintable::push_inlines(buf, title);
                 buf.push_str("\n");
             },
             Block::Paragraph(p) => Self::print_paragraph(buf, p),
-            Block::List(list) => IntoPrintable::print_list(buf, &list, 0),
+            Block::List(list) => IntoPrintable::print_list(buf, state, list),
+            Block::CodeBlock { code, .. } => IntoPrintable::print_code(buf, code),
+            Block::Math(m) => {
+                buf.push_str("$$");
+                buf.push_str(m);
+                buf.push_str("$$\n");
+            }
+            Block::Quote(blocks) => {
+                let mut content = String::new();
+
+                for block in blocks {
+                    IntoPrintable::print_block(&mut content, state, block);
+                }
+                for line in content.trim_end().lines() {
+                    buf.push('\u{2502}');
+
+                    if !line.is_empty() {
+                        buf.push(' ');
+                        buf.push_str(line);
+                    }
+                    buf.push('\n');
+                }
+            }
+            Block::Table { header, align, rows, caption, .. } => {
+                IntoPrintable::print_table(buf, header, align, rows);
+
+                if let Some(number) = number {
+                    buf.push_str(&format!("Table {}", number));
+
+                    if !caption.is_empty() {
+                        buf.push_str(": ");
+                        IntoPrintable::push_inlines(buf, caption);
+                    }
+                    buf.push('\n');
+                }
+            }
+            Block::Figure { path, caption, .. } => {
+                buf.push_str("[Figure");
+
+                if let Some(number) = number {
+                    buf.push(' ');
+                    buf.push_str(&number);
+                }
+                if !caption.is_empty() {
+                    buf.push_str(": ");
+                    IntoPrintable::push_inlines(buf, caption);
+                }
+                buf.push_str("] (");
+                buf.push_str(path);
+                buf.push_str(")\n");
+            }
+            Block::FootnoteDefinition(..) => (),
+            Block::TableOfContents => buf.push_str(&state.contents),
+            // only its format can show it, dimmed so it's not taken as text
+            Block::Raw { format, .. } => buf.push_str(&format!("\x1b[2m[{}]\x1b[22m\n", format)),
+        }
+    }
+    fn has_label(entries: &[OutlineEntry]) -> bool {
+        entries.iter().any(|e| e.label.is_some() || IntoPrintable::has_label(&e.children))
+    }
+    fn print_outline(buf: &mut String, entries: &[OutlineEntry], depth: usize) {
+        for entry in entries {
+            buf.push_str(&"  ".repeat(depth));
+            buf.push_str(&entry.number);
+            buf.push(' ');
+            IntoPrintable::push_inlines(buf, &entry.title);
+            buf.push('\n');
+
+            IntoPrintable::print_outline(buf, &entry.children, depth + 1);
         }
-        buf.push_str("\n");
     }
 }
 impl Compiler for IntoPrintable {
     fn compile(&mut self, document: &Document) -> String {
         let mut string = String::new();
+        let metadata = &document.metadata;
+
+        if let Some(title) = &metadata.title {
+            IntoPrintable::push_styled(&mut string, "\x1b[1m", "\x1b[22m", &[Inline::Text(title.clone())]);
+            string.push('\n');
+
+            for line in [&metadata.author, &metadata.date].iter().copied().flatten() {
+                string.push_str(line);
+                string.push('\n');
+            }
+            string.push('\n');
+        }
+        if let Some(a) = &metadata.r#abstract {
+            IntoPrintable::push_styled(&mut string, "\x1b[3m", "\x1b[23m", &[Inline::Text(a.clone())]);
+            string.push_str("\n\n");
+        }
+
+        let outline = document.outline();
+        let mut contents = String::new();
+        IntoPrintable::push_styled(&mut contents, "\x1b[1m", "\x1b[22m", &[Inline::Text(String::from("Contents"))]);
+        contents.push('\n');
+        IntoPrintable::print_outline(&mut contents, &outline, 0);
+
+        if metadata.toc {
+            string.push_str(&contents);
+            string.push('\n');
+        }
+        let mut state = PrintState {
+            numbering: Numbering::default(),
+            numbered: metadata.toc
+                || document.blocks.iter().any(|b| matches!(**b, Block::TableOfContents))
+                || IntoPrintable::has_label(&outline),
+            contents,
+        };
 
         for part in &document.blocks {
-            Self::print_block(&mut string, &part);
+            Self::print_block(&mut string, &mut state, part);
+        }
+        // the footnotes are collected at the end
+        for (i, text) in document.footnotes.iter().enumerate() {
+            string.push_str(&format!("[{}] ", i + 1));
+            IntoPrintable::push_inlines(&mut string, text);
+            string.push('\n');
+        }
+        if !self.color {
+            return IntoPrintable::strip(&string);
         }
         string
     }
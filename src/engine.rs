@@ -0,0 +1,114 @@
+use std::path::Path;
+use std::process::{Command, Output};
+use std::str::FromStr;
+use std::{fs, io};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EngineError {
+    #[error("Error running LaTeX engine: {0}")]
+    IoError(#[from] io::Error),
+    #[error("{0} isn't installed (or isn't in the PATH)")]
+    NotFound(String),
+    #[error("{engine} failed:\n{}", excerpt(.log))]
+    Failed {
+        engine: String,
+        /// The whole log of the engine.
+        log: String,
+    },
+}
+
+/// A LaTeX engine installed in the system, such as the ones of TeX Live.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Engine {
+    /// Runs the engine as many times as needed by itself.
+    Latexmk,
+    Pdflatex,
+    Xelatex,
+    Lualatex,
+}
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latexmk" => Ok(Engine::Latexmk),
+            "pdflatex" => Ok(Engine::Pdflatex),
+            "xelatex" => Ok(Engine::Xelatex),
+            "lualatex" => Ok(Engine::Lualatex),
+            _ => Err(format!("expected `latexmk`, `pdflatex`, `xelatex` or `lualatex`, found `{}`", s)),
+        }
+    }
+}
+
+// the lines of a LaTeX log that explain an error, which start with `!`,
+// or the end of the log if there are none
+pub(crate) fn excerpt(log: &str) -> String {
+    let lines: Vec<&str> = log.lines().collect();
+    let mut excerpt: Vec<&str> = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with('!') {
+            // the line of the source where it happened comes right after
+            excerpt.extend(lines[i..].iter().take(3));
+        }
+    }
+    if excerpt.is_empty() {
+        excerpt.extend(&lines[lines.len().saturating_sub(20)..]);
+    }
+    excerpt.join("\n")
+}
+
+impl Engine {
+    fn name(&self) -> &'static str {
+        match self {
+            Engine::Latexmk => "latexmk",
+            Engine::Pdflatex => "pdflatex",
+            Engine::Xelatex => "xelatex",
+            Engine::Lualatex => "lualatex",
+        }
+    }
+    fn run(&self, dir: &Path, tex: &Path) -> Result<Output, EngineError> {
+        let mut command = Command::new(self.name());
+
+        match self {
+            Engine::Latexmk => command.arg("-pdf").arg(format!("-outdir={}", dir.display())),
+            _ => command.arg(format!("-output-directory={}", dir.display())),
+        };
+        // run from the working directory, so that files are found the
+        // same way as with Tectonic
+        command
+            .arg("-interaction=nonstopmode")
+            .arg("-halt-on-error")
+            .arg(tex)
+            .output()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => EngineError::NotFound(self.name().to_string()),
+                _ => EngineError::IoError(e),
+            })
+    }
+    /// Compiles LaTeX code to binary PDF data in a temporary directory.
+    pub fn compile(&self, latex: &str) -> Result<Vec<u8>, EngineError> {
+        let dir = tempfile::tempdir()?;
+        let tex = dir.path().join("document.tex");
+        fs::write(&tex, latex)?;
+
+        // references and the table of contents need more than one run
+        for _ in 0..3 {
+            let output = self.run(dir.path(), &tex)?;
+            let log = fs::read_to_string(dir.path().join("document.log"))
+                .unwrap_or_else(|_| String::from_utf8_lossy(&output.stdout).into_owned());
+
+            if !output.status.success() {
+                return Err(EngineError::Failed {
+                    engine: self.name().to_string(),
+                    log,
+                });
+            }
+            if *self == Engine::Latexmk || !log.contains("Rerun to get") {
+                break;
+            }
+        }
+        Ok(fs::read(dir.path().join("document.pdf"))?)
+    }
+}
@@ -73,49 +73,65 @@
 
 mod parse;
 mod compiler;
+#[cfg(any(feature = "pdf", feature = "latex-engine", feature = "native-pdf"))]
 mod pdf;
+mod template;
+#[cfg(feature = "latex-engine")]
+mod engine;
+#[cfg(feature = "native-pdf")]
+mod render;
 
+#[cfg(any(feature = "pdf", feature = "latex-engine", feature = "native-pdf"))]
+pub use crate::pdf::{Backend, PdfError, save_as_pdf};
 pub use crate::{
-    pdf::{PdfError, save_as_pdf},
-    compiler::{Compiler, IntoLatex, IntoPrintable},
-    parse::{Document, parse}
+    compiler::{Compiler, IntoHtml, IntoLatex, IntoPrintable, LatexConfig},
+    template::{Template, TemplateError},
+    parse::{
+        Document, Block, Inline, List, ListItem, ListToken, TokenEnumerator, TokenWrapper,
+        Span, Spanned, Position, Diagnostic, Metadata, Indentation, ParseOptions,
+        parse, parse_with
+    }
 };
-use nom::Err as NomError;
-use nom::error::ErrorKind;
+#[cfg(feature = "latex-engine")]
+pub use crate::engine::{Engine, EngineError};
+#[cfg(feature = "native-pdf")]
+pub use crate::render::{Fonts, RenderError, render_pdf};
 use std::io::Error as IoError;
 
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum AldocError {
-    #[error("Error reading file")]
+    #[error("Error reading file: {0}")]
     FileError(#[from] IoError),
     #[error("Document is empty")]
     EmptyDocument,
-    #[error("Error parsing document")]
-    ParseError(NomError<(String, ErrorKind)>),
+    #[error("{0}")]
+    ParseError(#[from] Diagnostic),
+    #[error("{0}")]
+    TemplateError(#[from] TemplateError),
+    #[cfg(any(feature = "pdf", feature = "latex-engine", feature = "native-pdf"))]
     #[error("Error exporting to PDF: {0}")]
-    PdfError(#[from] PdfError)
-}
-impl From<NomError<(&str, ErrorKind)>> for AldocError {
-    fn from(e: NomError<(&str, ErrorKind)>) -> AldocError {
-        AldocError::ParseError(e.to_owned())
-    }
+    PdfError(#[from] PdfError),
 }
 
 #[cfg(test)]
 mod tests {
     use crate::AldocError;
+    #[cfg(feature = "pdf")]
     use crate::pdf::save_as_pdf;
     use crate::parse::parse;
     use std::path::PathBuf;
 
+    #[cfg(feature = "pdf")]
     fn quick_pdf<T>(p: T) -> Result<(), AldocError>
         where T: Into<PathBuf> 
     {
-        let text = std::fs::read_to_string(p.into()).unwrap();
+        let path = p.into();
+        let text = std::fs::read_to_string(&path).unwrap();
         let document = parse(&text)?;
-        save_as_pdf(&document, "test/test.pdf", true)?; // some tests don't need to be saved to a pdf
+        let root = path.parent().unwrap_or(&path);
+        save_as_pdf(&document, &Default::default(), root, "test/test.pdf", true)?; // some tests don't need to be saved to a pdf
         Ok(())
     }
     fn quick_parse<T>(p: T) -> Result<(), AldocError>
@@ -125,4 +141,713 @@ mod tests {
         parse(&text)?;
         Ok(())
     }
+    fn sublist(item: &crate::parse::ListItem) -> &crate::parse::List {
+        item.blocks.iter()
+            .find_map(|b| match &**b {
+                crate::parse::Block::List(list) => Some(list),
+                _ => None,
+            })
+            .expect("Expected a sublist")
+    }
+
+    #[test]
+    fn inline() {
+        use crate::parse::{parse_inlines, Block, Inline::*};
+
+        assert_eq!(
+            parse_inlines("a *b* **c *d*** `e*`"),
+            vec![
+                Text("a ".to_string()),
+                Emphasis(vec![Text("b".to_string())]),
+                Text(" ".to_string()),
+                Strong(vec![
+                    Text("c ".to_string()),
+                    Emphasis(vec![Text("d".to_string())]),
+                ]),
+                Text(" ".to_string()),
+                Code("e*".to_string()),
+            ]
+        );
+        assert_eq!(parse_inlines("2 * 3 * 4"), vec![Text("2 * 3 * 4".to_string())]);
+        quick_parse("examples/inline.ald").unwrap();
+
+        // a list token must be followed by whitespace, so that a paragraph
+        // can start with emphasis
+        let document = parse("*a* b\n\n-Alement\n\n- Alement\n").unwrap();
+        assert!(matches!(&*document.blocks[0], Block::Paragraph(_)));
+        assert!(matches!(&*document.blocks[1], Block::Paragraph(_)));
+        assert!(matches!(&*document.blocks[2], Block::List(_)));
+    }
+
+    #[test]
+    fn latex_escaping() {
+        use crate::compiler::{Compiler, IntoLatex};
+
+        let document = parse("50% of R&D: $5 #1 a_b {c} ~ ^ \\ <d> | e--f\n\n(1) Item").unwrap();
+        let latex = IntoLatex::default().compile(&document);
+
+        assert!(latex.contains(concat!(
+            r"50\% of R\&D: \$5 \#1 a\_b \{c\} \textasciitilde{} \textasciicircum{} ",
+            r"\textbackslash{} \textless{}d\textgreater{} \textbar{} e-{}-f"
+        )));
+        assert!(latex.contains(r"[label={(\arabic*)}]"));
+    }
+
+    #[test]
+    fn code_block() {
+        use crate::parse::Block;
+        use crate::compiler::{Compiler, IntoLatex};
+
+        let text = std::fs::read_to_string("examples/code.ald").unwrap();
+        let document = parse(&text).unwrap();
+
+        match &*document.blocks[2] {
+            Block::CodeBlock { lang, code } => {
+                assert_eq!(lang.as_deref(), Some("rust"));
+                assert!(code.contains("\"Hello,  world!\";\n\n\tprintln!"));
+            }
+            b => panic!("Expected a code block, found {:?}", b),
+        }
+        match &*document.blocks[3] {
+            Block::CodeBlock { lang: None, code } => assert_eq!(code, "No language here."),
+            b => panic!("Expected a code block, found {:?}", b),
+        }
+        assert_eq!(document.blocks.len(), 5);
+
+        // code that would end `verbatim` goes into fancyvrb's `Verbatim`
+        let document = parse("```latex\n\\begin{verbatim}\nx\n\\end{verbatim}\n```\n").unwrap();
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.contains("\\begin{Verbatim}\n\\begin{verbatim}\nx\n\\end{verbatim}\n\\end{Verbatim}\n"));
+
+        let error = parse("Text.\n\n```rust\nfn main() {}\n").unwrap_err();
+        assert_eq!(error.message, "unclosed code fence");
+        assert_eq!(error.position.line, 3);
+        assert_eq!(error.position.column, 1);
+    }
+
+    #[test]
+    fn math() {
+        use crate::compiler::{Compiler, IntoLatex};
+
+        let text = std::fs::read_to_string("examples/math.ald").unwrap();
+        let document = parse(&text).unwrap();
+        let latex = IntoLatex::default().compile(&document);
+
+        assert!(latex.contains(r"\(e^{i\pi} + 1 = 0\)"));
+        assert!(latex.contains(r"like \$5 and \$10 stay"));
+        assert!(latex.contains("\\[\n\\int_0^\\infty e^{-x^2} \\, dx = \\frac{\\sqrt{\\pi}}{2}\n\\]"));
+        assert_eq!(document.blocks.len(), 4);
+    }
+
+    #[test]
+    fn html() {
+        use crate::compiler::{Compiler, IntoHtml};
+
+        let document = parse("# A & B\n\n(a) One\n(b) <Two>\n\n- Three").unwrap();
+        let html = IntoHtml.compile(&document);
+
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("<h1 id=\"aldoc-section-1\">A &amp; B</h1>"));
+        assert!(html.contains(r#"<ol type="a" class="aldoc-list aldoc-label-0">"#));
+        assert!(html.contains("<li>&lt;Two&gt;</li>"));
+        assert!(html.contains(r#".aldoc-label-0 > li::before { content: "(" counter(aldoc-item, lower-alpha) ")"; }"#));
+        assert!(html.contains(r#"<ul class="aldoc-list aldoc-label-1">"#));
+    }
+
+    #[test]
+    fn spans() {
+        use crate::parse::{Block, Position};
+
+        let text = "# Title\n\nSome text\n\n- A\n- B\n\t- C\n";
+        let document = parse(text).unwrap();
+
+        let spans: Vec<&str> = document.blocks.iter()
+            .map(|b| &text[b.span.start..b.span.end])
+            .collect();
+        assert_eq!(spans, vec!["# Title", "Some text", "- A\n- B\n\t- C"]);
+
+        match &*document.blocks[2] {
+            Block::List(list) => {
+                let item = &list.vec[1];
+                assert_eq!(&text[item.span.start..item.span.end], "- B\n\t- C");
+
+                let sublist = sublist(item);
+                assert_eq!(&text[sublist.span.start..sublist.span.end], "- C");
+                assert_eq!(sublist.span.position(text), Position { line: 7, column: 2 });
+            }
+            b => panic!("Expected a list, found {:?}", b),
+        }
+    }
+
+    #[test]
+    fn diagnostics() {
+        let error = parse("Some text\n\n- A\n\t1) B\n\tc. C\n").unwrap_err();
+
+        assert_eq!(error.message, "list item uses `c.` but list started with `1)`");
+        assert_eq!(error.to_string(), concat!(
+            "error: list item uses `c.` but list started with `1)`\n",
+            " --> 5:2\n",
+            "  |\n",
+            "5 | \tc. C\n",
+            "  | \t^",
+        ));
+    }
+
+    #[test]
+    fn front_matter() {
+        use crate::compiler::{Compiler, IntoLatex};
+
+        let text = std::fs::read_to_string("examples/front_matter.ald").unwrap();
+        let document = parse(&text).unwrap();
+        let metadata = &document.metadata;
+
+        assert_eq!(metadata.title.as_deref(), Some("On the *Care* of Horses"));
+        assert_eq!(metadata.author.as_deref(), Some("Jane Doe & John Roe"));
+        assert_eq!(metadata.r#abstract.as_deref(), Some("A short study of horses and their utilities."));
+        assert_eq!(document.blocks.len(), 2);
+
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.starts_with("\\documentclass{report}"));
+        assert!(latex.contains("\\usepackage[english]{babel}"));
+        assert!(latex.contains("\\author{Jane Doe \\& John Roe}"));
+        assert!(latex.contains("\\begin{document}\n\\maketitle\n\\begin{abstract}"));
+
+        // books have no abstract, and babel doesn't know every language
+        let document = parse("---\nclass: book\nlang: zh\nabstract: A\n---\n").unwrap();
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.contains("\\begin{quote}\nA\n\\end{quote}\n"));
+        assert!(!latex.contains("babel"));
+
+        let text = std::fs::read_to_string("examples/front_matter_toml.ald").unwrap();
+        let document = parse(&text).unwrap();
+        assert_eq!(document.metadata.title.as_deref(), Some("Notes"));
+        assert_eq!(document.metadata.author.as_deref(), Some("Jane Doe"));
+
+        let error = parse("---\ntitle: A\nautor: B\n---\n").unwrap_err();
+        assert_eq!(error.message, "unknown front matter key `autor`");
+        assert_eq!(error.position.line, 3);
+
+        let error = parse("---\nclass: article}\\input{x\n---\n").unwrap_err();
+        assert_eq!(error.message, "expected letters, digits and `-` for `class`, found `article}\\input{x`");
+        assert!(parse("+++\nlang = \"es-MX\"\n+++\n").is_ok());
+    }
+
+    #[test]
+    fn enumerator_format() {
+        use crate::parse::TokenEnumerator::*;
+        use crate::compiler::{Compiler, IntoLatex};
+
+        let alphabetical: Vec<String> = [1, 6, 26, 27, 52, 53, 702, 703]
+            .iter()
+            .map(|&n| Alphabetical(false).format(n))
+            .collect();
+        assert_eq!(alphabetical, vec!["a", "f", "z", "aa", "az", "ba", "zz", "aaa"]);
+        assert_eq!(Alphabetical(true).format(28), "AB");
+
+        assert_eq!(Roman(true).format(14), "XIV");
+        assert_eq!(Roman(false).format(3999), "mmmcmxcix");
+        assert_eq!(Roman(false).format(4000), "4000");
+        assert_eq!(Numerical.format(40000), "40000");
+
+        // LaTeX can't count past `z`, so those labels are written out
+        let latex = IntoLatex::default().compile(&parse(&"a) x\n".repeat(27)).unwrap());
+        assert!(latex.contains("\\begin{enumerate}\n\\item[{a)}] x\n"));
+        assert!(latex.contains("\\item[{aa)}] x\n\\end{enumerate}"));
+
+        let latex = IntoLatex::default().compile(&parse(&"a) x\n".repeat(26)).unwrap());
+        assert!(latex.contains("\\begin{enumerate}[label={\\alph*)}]\n\\item x\n"));
+    }
+
+    #[test]
+    fn list_start() {
+        use crate::compiler::{Compiler, IntoLatex, IntoPrintable};
+        use crate::parse::{Block, TokenEnumerator};
+
+        let text = std::fs::read_to_string("examples/list_start.ald").unwrap();
+        let document = parse(&text).unwrap();
+
+        let lists: Vec<(Option<TokenEnumerator>, usize)> = document.blocks.iter()
+            .filter_map(|b| match &**b {
+                Block::List(l) => Some((l.token.enumerator.clone(), l.start)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lists, vec![
+            (Some(TokenEnumerator::Numerical), 3),
+            (Some(TokenEnumerator::Numerical), 5),
+            (Some(TokenEnumerator::Alphabetical(false)), 3),
+            (Some(TokenEnumerator::Roman(false)), 4),
+        ]);
+
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.contains(r"[label={\arabic*.}, start=5]"));
+
+        let printable = IntoPrintable::default().compile(&document);
+        assert!(printable.contains("5. Fifth\n6. Sixth"));
+        assert!(printable.contains("(c) Charlie\n(d) Delta"));
+        assert!(printable.contains("iv- Four\nv- Five\nvi- Six"));
+    }
+
+    #[test]
+    fn list_indentation() {
+        use crate::parse::{Block, Indentation, ParseOptions, parse_with};
+
+        let text = std::fs::read_to_string("examples/list_spaces.ald").unwrap();
+        let document = parse(&text).unwrap();
+
+        match &*document.blocks[1] {
+            Block::List(list) => {
+                assert_eq!(list.vec.len(), 2);
+
+                let fruits = sublist(&list.vec[0]);
+                assert_eq!(fruits.vec.len(), 2);
+                assert_eq!(sublist(&fruits.vec[1]).vec.len(), 2);
+            }
+            b => panic!("Expected a list, found {:?}", b),
+        }
+        assert_eq!(Indentation::detect(&text), Indentation::Spaces(2));
+        // items in a code block of an item don't count
+        let fenced = "- Code\n\n  ```\n    - not an item\n  ```\n- Items\n   - Nested\n";
+        assert_eq!(Indentation::detect(fenced), Indentation::Spaces(3));
+        assert_eq!("4".parse(), Ok(Indentation::Spaces(4)));
+
+        let options = ParseOptions { indentation: Indentation::Spaces(4) };
+        let error = parse_with(&text, &options).unwrap_err();
+        assert_eq!(error.message, "list item is indented with 2 spaces, but the document is indented with 4 spaces");
+        assert_eq!(error.position.line, 4);
+
+        let error = parse("- A\n  - B\n\t- C\n").unwrap_err();
+        assert_eq!(error.message, "list item is indented with tabs, but the document is indented with 2 spaces");
+    }
+
+    #[test]
+    fn list_blocks() {
+        use crate::parse::Block;
+        use crate::compiler::{Compiler, IntoLatex, IntoPrintable};
+
+        let text = std::fs::read_to_string("examples/list_blocks.ald").unwrap();
+        let document = parse(&text).unwrap();
+
+        match &*document.blocks[1] {
+            Block::List(list) => {
+                assert_eq!(list.vec.len(), 3);
+
+                let kinds: Vec<&str> = list.vec[0].blocks.iter()
+                    .map(|b| match &**b {
+                        Block::Paragraph(_) => "paragraph",
+                        Block::CodeBlock { .. } => "code",
+                        Block::List(_) => "list",
+                        _ => "other",
+                    })
+                    .collect();
+                assert_eq!(kinds, vec!["paragraph", "paragraph", "code"]);
+
+                let code = &list.vec[0].blocks[2];
+                assert_eq!(&text[code.span.start..code.span.end], "```\n\tcargo run -- doc.ald print\n\t```");
+                assert_eq!(sublist(&list.vec[1]).vec.len(), 2);
+            }
+            b => panic!("Expected a list, found {:?}", b),
+        }
+        // a list right below a paragraph
+        assert_eq!(parse("Fruits:\n- apples\n").unwrap().blocks.len(), 2);
+
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.contains("\\item Write the document.\n\nIt may span several lines.\n"));
+
+        // a bracket would be read as the label of the item
+        let latex = IntoLatex::default().compile(&parse("- [x] done\n").unwrap());
+        assert!(latex.contains("\\item {[}x] done\n"));
+
+        let printable = IntoPrintable::default().compile(&document);
+        assert!(printable.contains("1. Write the document.\n\n  It may span several lines.\n\n      cargo run"));
+    }
+
+    #[test]
+    fn quote() {
+        use crate::parse::Block;
+
+        let text = std::fs::read_to_string("examples/quote.ald").unwrap();
+        let document = parse(&text).unwrap();
+        assert_eq!(document.blocks.len(), 4);
+
+        match &*document.blocks[2] {
+            Block::Quote(blocks) => {
+                assert_eq!(blocks.len(), 3);
+                assert!(matches!(*blocks[1], Block::List(_)));
+
+                let nested = &blocks[2];
+                assert_eq!(&text[nested.span.start..nested.span.end], "> Any dispute shall be settled in court.");
+                assert!(matches!(&**nested, Block::Quote(b) if b.len() == 1));
+            }
+            b => panic!("Expected a quote, found {:?}", b),
+        }
+    }
+
+    #[test]
+    fn table() {
+        use crate::parse::{Block, Alignment, Inline};
+        use crate::compiler::{Compiler, IntoLatex, IntoPrintable};
+
+        let text = std::fs::read_to_string("examples/table.ald").unwrap();
+        let document = parse(&text).unwrap();
+
+        match &*document.blocks[1] {
+            Block::Table { header, align, rows, .. } => {
+                assert_eq!(header.len(), 3);
+                assert_eq!(align, &vec![Alignment::Left, Alignment::Center, Alignment::Right]);
+                assert_eq!(rows.len(), 3);
+                assert_eq!(rows[1][0], vec![Inline::Emphasis(vec![Inline::Text("Orange".to_string())])]);
+            }
+            b => panic!("Expected a table, found {:?}", b),
+        }
+        // escaped pipes and missing cells
+        match &*parse("a | b\n--- | ---\nx \\| y\n").unwrap().blocks[0] {
+            Block::Table { rows, .. } => {
+                assert_eq!(rows[0][0], vec![Inline::Text("x | y".to_string())]);
+                assert!(rows[0][1].is_empty());
+            }
+            b => panic!("Expected a table, found {:?}", b),
+        }
+
+        let printable = IntoPrintable::default().compile(&document);
+        assert!(printable.contains("\u{2502} 梨 (pear) \u{2502}  日本  \u{2502}      3.50 \u{2502}\n"));
+
+        // a bracket after `\\` would be read as a length
+        let latex = IntoLatex::default().compile(&parse("a | b\n--- | ---\n[1] | z\n").unwrap());
+        assert!(latex.contains("\\midrule\n{[}1] & z \\\\\n"));
+    }
+
+    #[test]
+    fn figure() {
+        use crate::parse::Block;
+        use crate::compiler::{Compiler, IntoLatex};
+
+        let text = std::fs::read_to_string("examples/figure.ald").unwrap();
+        let document = parse(&text).unwrap();
+
+        match &*document.blocks[2] {
+            Block::Figure { path, caption, label, width, height } => {
+                assert_eq!(path, "images/dot.png");
+                assert_eq!(caption.len(), 3);
+                assert_eq!(label.as_deref(), Some("fig:dot"));
+                assert_eq!(width.as_deref(), Some("50%"));
+                assert_eq!(height, &None);
+            }
+            b => panic!("Expected a figure, found {:?}", b),
+        }
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.contains("\\includegraphics[width=0.5\\linewidth]{images/dot.png}\n\\caption{A \\emph{blue} dot}\n\\label{fig:dot}\n"));
+
+        let error = parse("![Dot](dot.png){size=2}\n").unwrap_err();
+        assert_eq!(error.message, "unknown figure attribute `size`");
+        assert_eq!(error.position.column, 17);
+
+        let error = parse("![Dot](100%.png)\n").unwrap_err();
+        assert_eq!(error.message, "figure paths can't contain `%`, `#`, `{` or `}`");
+        assert_eq!(error.position.column, 11);
+
+        let document = parse("![Dot](dot.png){width=4.5cm height=.5%}\n").unwrap();
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.contains("\\includegraphics[width=4.5cm, height=0.005\\textheight, keepaspectratio]{dot.png}\n"));
+
+        let error = parse("![Dot](dot.png){width=1\\textwidth}\n").unwrap_err();
+        assert_eq!(error.message, "figure width `1\\textwidth` isn't a length like `4cm` or `50%`");
+        assert_eq!(error.position.column, 23);
+    }
+
+    #[test]
+    fn links() {
+        use crate::parse::{parse_inlines, Inline::*};
+        use crate::compiler::{Compiler, IntoLatex};
+
+        assert_eq!(
+            parse_inlines("see [the *docs*](https://a.b/c_d) or <https://e.f>"),
+            vec![
+                Text("see ".to_string()),
+                Link(
+                    vec![Text("the ".to_string()), Emphasis(vec![Text("docs".to_string())])],
+                    "https://a.b/c_d".to_string()
+                ),
+                Text(" or ".to_string()),
+                Url("https://e.f".to_string()),
+            ]
+        );
+        // not links
+        assert_eq!(parse_inlines("[a] (b) <c>"), vec![Text("[a] (b) <c>".to_string())]);
+
+        let text = std::fs::read_to_string("examples/links.ald").unwrap();
+        let latex = IntoLatex::default().compile(&parse(&text).unwrap());
+        assert!(latex.contains("\\href{https://example.com/search?q=safe_parsers\\#top}{100\\% safe parsers}"));
+        assert!(latex.contains("\\url{https://github.com/Sinono3/aldoc}"));
+    }
+
+    #[test]
+    fn footnotes() {
+        use crate::parse::{Inline, Block};
+        use crate::compiler::{Compiler, IntoLatex, IntoPrintable};
+
+        let text = std::fs::read_to_string("examples/footnotes.ald").unwrap();
+        let document = parse(&text).unwrap();
+
+        // the definitions are taken out of the document
+        assert_eq!(document.blocks.len(), 3);
+        assert_eq!(document.footnotes.len(), 2);
+
+        match &*document.blocks[1] {
+            Block::Paragraph(p) => assert!(p.contains(&Inline::Footnote(2, document.footnotes[1].clone()))),
+            b => panic!("Expected a paragraph, found {:?}", b),
+        }
+
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.contains("Nom\\footnote{A parser combinators library, which is \\emph{very} fast.}."));
+
+        let printable = IntoPrintable::default().compile(&document);
+        assert!(printable.contains("lists too[2]."));
+        assert!(printable.ends_with("[1] See \x1b[4mhttps://tectonic-typesetting.github.io\x1b[24m.\n[2] A parser combinators library, which is \x1b[3mvery\x1b[23m fast.\n"));
+        let plain = IntoPrintable::new(false).compile(&document);
+        assert!(plain.ends_with("[1] See https://tectonic-typesetting.github.io.\n[2] A parser combinators library, which is very fast.\n"));
+
+        let error = parse("Text[^a] and[^b].\n\n[^a]: A\n").unwrap_err();
+        assert_eq!(error.message, "footnote `b` is not defined");
+        assert_eq!(error.position.column, 13);
+
+        // an unused footnote is only a warning
+        let document = parse("Text[^a].\n\n[^a]: A\n[^c]: C\n").unwrap();
+        assert_eq!(document.footnotes.len(), 1);
+        assert_eq!(document.warnings.len(), 1);
+        assert_eq!(document.warnings[0].message, "footnote `c` is never referenced");
+        assert_eq!(document.warnings[0].position.line, 4);
+        assert!(document.warnings[0].to_string().starts_with("warning: "));
+    }
+
+    #[test]
+    fn references() {
+        use crate::parse::{Block, Inline};
+        use crate::compiler::{Compiler, IntoPrintable};
+
+        let text = std::fs::read_to_string("examples/references.ald").unwrap();
+        let document = parse(&text).unwrap();
+
+        match &*document.blocks[0] {
+            Block::Heading(1, _, label) => assert_eq!(label.as_deref(), Some("sec:intro")),
+            b => panic!("Expected a heading, found {:?}", b),
+        }
+        match &*document.blocks[1] {
+            Block::Paragraph(p) => {
+                assert!(p.contains(&Inline::Reference("tbl:prices".to_string(), Some("1".to_string()))));
+                // an email address isn't a reference
+                assert!(matches!(p.last(), Some(Inline::Text(t)) if t.ends_with("docs@example.com.")));
+            }
+            b => panic!("Expected a paragraph, found {:?}", b),
+        }
+        assert_eq!(
+            document.footnotes[0][1], 
+            Inline::Reference("sec:details".to_string(), Some("1.1".to_string()))
+        );
+
+        // without a `kind:` prefix it's only a reference if the label exists
+        let document = parse("# A {#a}\n\nSee @a, or ping @jane.\n").unwrap();
+        match &*document.blocks[1] {
+            Block::Paragraph(p) => {
+                assert_eq!(p[1], Inline::Reference("a".to_string(), Some("1".to_string())));
+                assert_eq!(p[3], Inline::Text("@jane".to_string()));
+            }
+            b => panic!("Expected a paragraph, found {:?}", b),
+        }
+        assert_eq!(document.warnings[0].message, "`@jane` isn't a label, so it's kept as text");
+        assert_eq!(document.warnings[0].position.column, 17);
+
+        // headings are only numbered in the terminal when they have labels
+        let printable = IntoPrintable::default().compile(&document);
+        assert!(printable.starts_with("# 1 A\n"));
+        let printable = IntoPrintable::default().compile(&parse("# A\n\n## B\n").unwrap());
+        assert!(printable.starts_with("# A\n\n## B\n"));
+
+        let error = parse("# A {#a}\n\nSee @a and @sec:b.\n").unwrap_err();
+        assert_eq!(error.message, "reference to undefined label `sec:b`");
+        assert_eq!(error.position, crate::parse::Position { line: 3, column: 12 });
+
+        let error = parse("# A {#a}\n\n# B {#a}\n").unwrap_err();
+        assert_eq!(error.message, "label `a` is defined more than once");
+        assert_eq!(error.position, crate::parse::Position { line: 3, column: 6 });
+    }
+
+    #[test]
+    fn outline() {
+        use crate::parse::Block;
+        use crate::compiler::{Compiler, IntoHtml, IntoLatex, IntoPrintable};
+
+        let text = std::fs::read_to_string("examples/toc.ald").unwrap();
+        let document = parse(&text).unwrap();
+        assert!(document.metadata.toc);
+
+        let outline = document.outline();
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].label.as_deref(), Some("sec:install"));
+        assert_eq!(outline[0].children.len(), 2);
+        assert_eq!(outline[1].children[0].number, "2.0.1");
+        assert_eq!(outline[1].children[1].number, "2.1");
+
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.contains("\\maketitle\n\\tableofcontents\n"));
+
+        // every entry links to its heading
+        let html = IntoHtml.compile(&document);
+        assert!(html.contains("<li><a href=\"#sec:install\">1 Installation</a>"));
+        assert!(html.contains("<li><a href=\"#aldoc-section-2.1\">2.1 Compiling</a></li>"));
+        assert!(html.contains("<h2 id=\"aldoc-section-2.1\">Compiling</h2>"));
+
+        let document = parse("[TOC]\n\n# A\n\n## B\n").unwrap();
+        assert!(matches!(*document.blocks[0], Block::TableOfContents));
+
+        let printable = IntoPrintable::default().compile(&document);
+        assert!(printable.starts_with("\x1b[1mContents\x1b[22m\n1 A\n  1.1 B\n"));
+
+        let error = parse("---\ntoc: yes\n---\n").unwrap_err();
+        assert_eq!(error.message, "expected `true` or `false` for `toc`, found `yes`");
+    }
+
+    #[test]
+    fn sections() {
+        use crate::compiler::{Compiler, IntoLatex, LatexConfig};
+
+        let text = "# A\n\n## B\n\n####### G\n";
+        let document = parse(text).unwrap();
+
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.starts_with("\\documentclass{article}"));
+        assert!(latex.contains("\\section{A}\n\n\\subsection{B}\n\n\\subparagraph{G}\n"));
+
+        let config = LatexConfig { class: String::from("book"), ..Default::default() };
+        let latex = IntoLatex::new(config).compile(&document);
+        assert!(latex.starts_with("\\documentclass{book}"));
+        assert!(latex.contains("\\chapter{A}\n\n\\section{B}\n"));
+
+        // the class of the front matter takes precedence
+        let document = parse(&format!("---\nclass: report\n---\n{}", text)).unwrap();
+        let config = LatexConfig { 
+            sections: vec![String::from("part"), String::from("chapter")], 
+            ..Default::default() 
+        };
+        let latex = IntoLatex::new(config).compile(&document);
+        assert!(latex.starts_with("\\documentclass{report}"));
+        assert!(latex.contains("\\part{A}\n\n\\chapter{B}\n\n\\chapter{G}\n"));
+    }
+
+    #[test]
+    fn template() {
+        use crate::compiler::{Compiler, IntoLatex, LatexConfig};
+        use crate::template::{Template, TemplateError};
+
+        let text = "---\ntitle: A & B\nauthor: C\n---\n\n# A\n";
+        let document = parse(text).unwrap();
+
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.starts_with("\\documentclass{article}\n\n\\usepackage{enumitem}\n"));
+        assert!(latex.contains("\\date{}\n\n\\begin{document}\n\\maketitle\n\n\\section{A}\n\\end{document}\n"));
+
+        let template = Template::from_file("examples/template.tex").unwrap();
+        let config = LatexConfig { template, ..Default::default() };
+        let latex = IntoLatex::new(config).compile(&document);
+        assert!(latex.contains("\\fancyhead[L]{A \\& B}\n\\fancyhead[R]{C}\n"));
+        // the language is left out instead of leaving a blank line
+        assert!(latex.contains("{article}\n\n\\usepackage[margin=2.5cm]{geometry}\n"));
+
+        let template = Template::new("$$x$$ $body$").unwrap();
+        let config = LatexConfig { template, ..Default::default() };
+        assert!(IntoLatex::new(config).compile(&document).starts_with("$x$ \\maketitle"));
+
+        assert!(matches!(
+            Template::new("\n$body$ $logo$"),
+            Err(TemplateError::UnknownPlaceholder { line: 2, .. })
+        ));
+        assert!(matches!(Template::new("$body$ 5$"), Err(TemplateError::Unclosed(1))));
+        assert!(matches!(Template::new("$title$"), Err(TemplateError::MissingBody)));
+    }
+
+    #[test]
+    fn raw() {
+        use crate::parse::{Block, Inline};
+        use crate::compiler::{Compiler, IntoHtml, IntoLatex, IntoPrintable};
+
+        let text = std::fs::read_to_string("examples/raw.ald").unwrap();
+        let document = parse(&text).unwrap();
+
+        match &*document.blocks[1] {
+            Block::Paragraph(p) => assert_eq!(p[1], Inline::Raw("latex".to_string(), "\\vspace{1em}".to_string())),
+            _ => panic!("Expected a paragraph"),
+        }
+        assert!(matches!(&*document.blocks[2], Block::Raw { format, .. } if format == "latex"));
+
+        let latex = IntoLatex::default().compile(&document);
+        assert!(latex.contains("Some text, \\vspace{1em} and some more.\n"));
+        assert!(latex.contains("\\begin{center}\n\\rule{0.5\\linewidth}{0.4pt}\n\\end{center}\n"));
+        assert!(latex.contains("\\newpage\n"));
+        assert!(!latex.contains("<hr>"));
+
+        let html = IntoHtml.compile(&document);
+        assert!(html.contains("<p>Some text,  and some more.</p>\n<hr>\n"));
+        assert!(!html.contains("rule"));
+
+        let printable = IntoPrintable::default().compile(&document);
+        assert!(printable.contains("\x1b[2m[latex]\x1b[22m\n"));
+        assert!(!printable.contains("newpage"));
+
+        // an info string that isn't a format is the language of a code block
+        let document = parse("```{=tex2}\na\n```\n").unwrap();
+        assert!(matches!(&*document.blocks[0], Block::CodeBlock { .. }));
+    }
+
+    #[test]
+    #[cfg(feature = "native-pdf")]
+    fn native_fonts() {
+        use crate::render::{Fonts, RenderError};
+
+        assert!(matches!(Fonts::family("examples/Missing"), Err(RenderError::MissingFont(_))));
+
+        let invalid = std::env::temp_dir().join("aldoc-invalid-font");
+        std::fs::write(invalid.with_extension("ttf"), "not a font").unwrap();
+        assert!(matches!(Fonts::family(&invalid), Err(RenderError::InvalidFont(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "native-pdf")]
+    #[ignore = "needs serif and monospace fonts installed in the system"]
+    fn native_pdf() {
+        use crate::render::{Fonts, render_pdf};
+
+        let fonts = Fonts::system().unwrap();
+
+        for example in &["toc", "list_blocks", "table", "footnotes", "references", "raw"] {
+            let text = std::fs::read_to_string(format!("examples/{}.ald", example)).unwrap();
+            let document = parse(&text).unwrap();
+
+            let pdf = render_pdf(&document, &fonts).unwrap();
+            assert!(pdf.starts_with(b"%PDF-"));
+        }
+
+        // images aren't drawn, so they don't have to exist
+        let document = parse("![A dot](dot.png)\n").unwrap();
+        let backend = crate::pdf::Backend::Native(fonts);
+        assert!(crate::pdf::compile_to_pdf(&document, &backend, std::path::Path::new("missing")).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "latex-engine")]
+    fn latex_engine() {
+        use crate::engine::{Engine, EngineError, excerpt};
+
+        assert_eq!("xelatex".parse::<Engine>(), Ok(Engine::Xelatex));
+        assert_eq!(
+            "tectonic".parse::<Engine>(),
+            Err("expected `latexmk`, `pdflatex`, `xelatex` or `lualatex`, found `tectonic`".to_string())
+        );
+
+        let log = "This is pdfTeX\n(./document.tex\n! Undefined control sequence.\nl.12 \\foo\n\n)\nNo pages of output.\n";
+        assert_eq!(excerpt(log), "! Undefined control sequence.\nl.12 \\foo\n");
+        assert_eq!(excerpt("a\nb"), "a\nb");
+
+        let error = EngineError::Failed { engine: "pdflatex".to_string(), log: log.to_string() };
+        assert!(error.to_string().starts_with("pdflatex failed:\n! Undefined control sequence.\n"));
+    }
 }
@@ -1,7 +1,15 @@
-use aldoc::{AldocError, parse, save_as_pdf};
-use aldoc::{IntoLatex, IntoPrintable, Compiler};
+use aldoc::{AldocError, Indentation, ParseOptions, parse_with};
+use aldoc::{IntoHtml, IntoLatex, IntoPrintable, Compiler, LatexConfig, Template};
+#[cfg(any(feature = "pdf", feature = "latex-engine", feature = "native-pdf"))]
+use aldoc::{Backend, save_as_pdf};
+#[cfg(feature = "latex-engine")]
+use aldoc::Engine;
+#[cfg(feature = "native-pdf")]
+use aldoc::{Fonts, PdfError};
 use std::fs;
+use std::io::{self, IsTerminal};
 use std::path::PathBuf;
+use std::process;
 use clap::Clap;
 
 /// A markup language compiler.
@@ -10,15 +18,23 @@ use clap::Clap;
 struct Aldoc {
     /// Input .md file
     input: PathBuf,
+    /// Indentation of nested lists: `tabs`, a number of spaces or `auto`.
+    #[clap(long, default_value = "auto")]
+    indent: Indentation,
+    /// LaTeX template the document is put into.
+    #[clap(long)]
+    template: Option<PathBuf>,
     #[clap(subcommand)]
     subcommand: Subcommand,
 }
 #[derive(Clap)]
 enum Subcommand {
+    #[cfg(any(feature = "pdf", feature = "latex-engine", feature = "native-pdf"))]
     Compile(Compile),
     Print(Print),
 }
 /// Compiles the document to a PDF.
+#[cfg(any(feature = "pdf", feature = "latex-engine", feature = "native-pdf"))]
 #[derive(Clap)]
 struct Compile {
     /// PDF output path (defaults to the input file with a pdf extension).
@@ -26,27 +42,72 @@ struct Compile {
     /// Determines if the output file will be overwritten
     #[clap(short, long)]
     force: bool,
+    /// Compiles with a LaTeX engine installed in the system instead of 
+    /// Tectonic: `latexmk`, `pdflatex`, `xelatex` or `lualatex`.
+    #[cfg(feature = "latex-engine")]
+    #[clap(long)]
+    #[cfg_attr(feature = "native-pdf", clap(conflicts_with_all = &["native", "font"]))]
+    engine: Option<Engine>,
+    /// Lays out the PDF directly instead of going through LaTeX.
+    #[cfg(feature = "native-pdf")]
+    #[clap(long)]
+    native: bool,
+    /// Font family for --native (implies it), such as `fonts/DejaVuSerif` 
+    /// for `fonts/DejaVuSerif.ttf`, `fonts/DejaVuSerif-Bold.ttf`, etc.
+    #[cfg(feature = "native-pdf")]
+    #[clap(long)]
+    font: Option<PathBuf>,
+    /// Font family for code with --native.
+    #[cfg(feature = "native-pdf")]
+    #[clap(long, requires = "font")]
+    mono_font: Option<PathBuf>,
 }
 /// Prints the document to STDOUT.
 #[derive(Clap)]
 struct Print {
     /// Should the document be printed in LaTeX format?
     #[clap(short, long)]
-    latex: bool
+    latex: bool,
+    /// Should the document be printed in HTML format?
+    #[clap(long, conflicts_with = "latex")]
+    html: bool,
 }
 
-fn main() -> Result<(), AldocError> {
+fn main() {
     let aldoc: Aldoc = Aldoc::parse();
+
+    if let Err(e) = run(aldoc) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run(aldoc: Aldoc) -> Result<(), AldocError> {
     let text = fs::read_to_string(&aldoc.input)?;
 
     if text.is_empty() {
         return Err(AldocError::EmptyDocument);
     }
 
-    let document = parse(&text)?;
+    let options = ParseOptions {
+        indentation: aldoc.indent,
+    };
+    let document = parse_with(&text, &options)?;
+
+    for warning in &document.warnings {
+        eprintln!("{}", warning);
+    }
+
+    let mut config = LatexConfig::default();
+
+    if let Some(path) = &aldoc.template {
+        config.template = Template::from_file(path)?;
+    }
 
     match aldoc.subcommand {
+        #[cfg(any(feature = "pdf", feature = "latex-engine", feature = "native-pdf"))]
         Subcommand::Compile(c) => {
+            let backend = backend(&c, config)?;
             let output = if let Some(o) = c.output {
                 o
             } else {
@@ -55,18 +116,63 @@ fn main() -> Result<(), AldocError> {
                 path
             };
 
-            save_as_pdf(&document, output, c.force)?;
+            // figures are relative to the document
+            let root = aldoc.input.parent().unwrap_or(&aldoc.input);
+            save_as_pdf(&document, &backend, root, output, c.force)?;
         }
         Subcommand::Print(print) => {
             let text = if print.latex {
-                IntoLatex.compile(&document)
+                IntoLatex::new(config).compile(&document)
+            } else if print.html {
+                IntoHtml.compile(&document)
             } else {
-                IntoPrintable.compile(&document)
+                // styling is left out of pipes and files, and when asked
+                let color = io::stdout().is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none();
+                IntoPrintable::new(color).compile(&document)
             };
             println!("{}", text);
         }
     }
-    // TODO: print `Display` text instead of `Debug` text
     Ok(())
 }
 
+// the backend asked for, otherwise the default one (which flags exist
+// depends on the features)
+#[cfg(any(feature = "pdf", feature = "latex-engine", feature = "native-pdf"))]
+fn backend(compile: &Compile, config: LatexConfig) -> Result<Backend, AldocError> {
+    #[cfg(feature = "native-pdf")]
+    if compile.native || compile.font.is_some() {
+        return native_backend(compile);
+    }
+    #[cfg(feature = "latex-engine")]
+    if let Some(engine) = compile.engine {
+        return Ok(Backend::Engine(engine, config));
+    }
+    default_backend(compile, config)
+}
+// the first one built of Tectonic, latexmk and the native one
+#[cfg(feature = "pdf")]
+fn default_backend(_: &Compile, config: LatexConfig) -> Result<Backend, AldocError> {
+    Ok(Backend::Tectonic(config))
+}
+#[cfg(all(not(feature = "pdf"), feature = "latex-engine"))]
+fn default_backend(_: &Compile, config: LatexConfig) -> Result<Backend, AldocError> {
+    Ok(Backend::Engine(Engine::Latexmk, config))
+}
+#[cfg(all(not(any(feature = "pdf", feature = "latex-engine")), feature = "native-pdf"))]
+fn default_backend(compile: &Compile, _: LatexConfig) -> Result<Backend, AldocError> {
+    native_backend(compile)
+}
+#[cfg(feature = "native-pdf")]
+fn native_backend(compile: &Compile) -> Result<Backend, AldocError> {
+    let fonts = match &compile.font {
+        Some(font) => Fonts::family(font),
+        None => Fonts::system(),
+    };
+    let fonts = match &compile.mono_font {
+        Some(mono) => fonts.and_then(|fonts| fonts.with_monospace(mono)),
+        None => fonts,
+    };
+    Ok(Backend::Native(fonts.map_err(PdfError::from)?))
+}
@@ -0,0 +1,66 @@
+use nom::{
+    bytes::complete::{tag, take_till1},
+    combinator::map,
+    multi::many0,
+    sequence::{delimited, preceded},
+    character::complete::multispace0,
+};
+use super::IResult;
+
+/// Attributes written after a block, like `{#label width=50%}`.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Attributes {
+    /// The label given with `#`.
+    pub id: Option<String>,
+    /// The `key=value` pairs, in the order they were written.
+    pub pairs: Vec<(String, String)>,
+}
+
+fn attribute(input: &str) -> IResult<&str, &str> {
+    preceded(
+        multispace0,
+        take_till1(|c: char| c.is_whitespace() || c == '}')
+    )(input)
+}
+
+/// Parses attributes between braces, a bare word being kept as a key
+/// with an empty value.
+pub fn parse_attributes(input: &str) -> IResult<&str, Attributes> {
+    map(
+        delimited(
+            tag("{"),
+            many0(attribute),
+            preceded(multispace0, tag("}"))
+        ),
+        |words: Vec<&str>| {
+            let mut attributes = Attributes::default();
+
+            for word in words {
+                if let Some(id) = word.strip_prefix('#') {
+                    attributes.id = Some(id.to_string());
+                    continue;
+                }
+                let (key, value) = match word.find('=') {
+                    Some(i) => (&word[..i], word[i + 1..].trim_matches('"')),
+                    None => (word, ""),
+                };
+                attributes.pairs.push((key.to_string(), value.to_string()));
+            }
+            attributes
+        }
+    )(input)
+}
+
+/// Splits a trailing `{#label}` off a line of text.
+pub fn split_label(text: &str) -> (&str, Option<String>) {
+    let trimmed = text.trim_end();
+
+    if let Some(i) = trimmed.rfind('{') {
+        if let Ok(("", attributes)) = parse_attributes(&trimmed[i..]) {
+            if attributes.id.is_some() && attributes.pairs.is_empty() {
+                return (trimmed[..i].trim_end(), attributes.id);
+            }
+        }
+    }
+    (text, None)
+}
@@ -0,0 +1,37 @@
+use super::take_until_match;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till1},
+    combinator::{opt, map},
+    multi::many0_count,
+    sequence::{delimited, preceded, terminated, pair},
+    character::complete::{line_ending, space0},
+};
+use super::{Error, IResult};
+
+fn fence(input: &str) -> IResult<&str, &str> {
+    tag("```")(input)
+}
+
+fn closing_fence(input: &str) -> IResult<&str, &str> {
+    preceded(line_ending, fence)(input)
+}
+
+/// Parses a fenced block, returning its info string (text after the opening 
+/// fence) and its contents, which are kept verbatim.
+pub fn parse_fenced(input: &str) -> IResult<&str, (Option<&str>, String)> {
+    let (rest, info) = delimited(
+        fence,
+        opt(preceded(space0, take_till1(|c: char| c.is_whitespace()))),
+        pair(space0, line_ending)
+    )(input)?;
+    // past the opening fence, a missing closing one is the user's mistake
+    let (rest, code) = alt((
+        // the closing fence on the line right after the opening one
+        map(fence, |_| ""),
+        terminated(take_until_match(closing_fence), closing_fence),
+    ))(rest).map_err(|_| Error::failure(input, String::from("unclosed code fence")))?;
+    let (rest, _) = pair(space0, many0_count(line_ending))(rest)?;
+
+    Ok((rest, (info, code.replace("\r\n", "\n"))))
+}
@@ -0,0 +1,97 @@
+use super::Position;
+use nom::error::{ErrorKind, ParseError};
+use nom::Offset;
+use std::fmt;
+
+/// The result of the block parsers.
+pub type IResult<I, O> = nom::IResult<I, O, Error<I>>;
+
+/// A parser error which may carry a message for the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error<I> {
+    pub input: I,
+    pub kind: ErrorKind,
+    pub message: Option<String>,
+}
+impl<I> Error<I> {
+    /// An error that stops the parsing and is reported to the user.
+    pub fn failure(input: I, message: String) -> nom::Err<Error<I>> {
+        nom::Err::Failure(Error {
+            input,
+            kind: ErrorKind::Verify,
+            message: Some(message),
+        })
+    }
+}
+impl<I> ParseError<I> for Error<I> {
+    fn from_error_kind(input: I, kind: ErrorKind) -> Self {
+        Error { input, kind, message: None }
+    }
+    fn append(_: I, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+/// A message pointing at a place in the source text.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub position: Position,
+    /// The source line that the position is in.
+    pub line: String,
+    /// Whether it's only a warning, which doesn't stop the parsing.
+    pub warning: bool,
+}
+impl Diagnostic {
+    pub fn new(source: &str, offset: usize, message: String) -> Diagnostic {
+        let line_start = source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = source[line_start..].lines().next().unwrap_or("");
+
+        Diagnostic {
+            message,
+            position: Position::new(source, offset),
+            line: line.trim_end().to_string(),
+            warning: false,
+        }
+    }
+    pub fn warning(source: &str, offset: usize, message: String) -> Diagnostic {
+        Diagnostic {
+            warning: true,
+            ..Diagnostic::new(source, offset, message)
+        }
+    }
+    /// Converts the error of a parser that ran over `source`.
+    pub fn from_error(source: &str, error: nom::Err<Error<&str>>) -> Diagnostic {
+        match error {
+            nom::Err::Error(e) | nom::Err::Failure(e) => {
+                let kind = e.kind;
+                let message = e.message
+                    .unwrap_or_else(|| format!("unexpected input ({:?})", kind));
+                Diagnostic::new(source, source.offset(e.input), message)
+            }
+            nom::Err::Incomplete(_) => {
+                Diagnostic::new(source, source.len(), String::from("unexpected end of document"))
+            }
+        }
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let number = self.position.line.to_string();
+        let gutter = " ".repeat(number.len());
+        // tabs are kept so that the caret lines up with the source line
+        let padding: String = self.line.chars()
+            .take(self.position.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let severity = if self.warning { "warning" } else { "error" };
+
+        writeln!(f, "{}: {}", severity, self.message)?;
+        writeln!(f, "{}--> {}:{}", gutter, self.position.line, self.position.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", number, self.line)?;
+        write!(f, "{} | {}^", gutter, padding)
+    }
+}
+impl std::error::Error for Diagnostic {}
@@ -0,0 +1,98 @@
+use super::{for_each_inlines, Block, Inline, Span, Spanned, Diagnostic, Document};
+
+struct Definition {
+    id: String,
+    text: Vec<Inline>,
+    span: Span,
+    number: Option<usize>,
+}
+
+// takes the footnote definitions out of the blocks
+fn take_definitions(blocks: &mut Vec<Spanned<Block>>, found: &mut Vec<Definition>) {
+    let mut kept = Vec::with_capacity(blocks.len());
+
+    for mut block in blocks.drain(..) {
+        if let Block::FootnoteDefinition(id, text) = block.node {
+            found.push(Definition { id, text, span: block.span, number: None });
+            continue;
+        }
+        for children in block.node.children_mut() {
+            take_definitions(children, found);
+        }
+        kept.push(block);
+    }
+    *blocks = kept;
+}
+
+fn has_reference(inlines: &[Inline]) -> bool {
+    inlines.iter().any(|inline| match inline {
+        Inline::FootnoteReference(_) => true,
+        Inline::Emphasis(i) | Inline::Strong(i) | Inline::Link(i, _) => has_reference(i),
+        _ => false,
+    })
+}
+
+// replaces the references with their footnotes, returning the id of
+// the first reference without a definition
+fn resolve(inlines: &mut Vec<Inline>, definitions: &mut [Definition], footnotes: &mut Vec<Vec<Inline>>) -> Result<(), String> {
+    for inline in inlines {
+        match inline {
+            Inline::FootnoteReference(id) => {
+                let definition = definitions.iter_mut()
+                    .find(|d| d.id == *id)
+                    .ok_or_else(|| id.clone())?;
+                // footnotes are numbered by their first reference
+                let number = match definition.number {
+                    Some(number) => number,
+                    None => {
+                        footnotes.push(definition.text.clone());
+                        definition.number = Some(footnotes.len());
+                        footnotes.len()
+                    }
+                };
+                *inline = Inline::Footnote(number, definition.text.clone());
+            }
+            Inline::Emphasis(i) | Inline::Strong(i) | Inline::Link(i, _) => 
+                resolve(i, definitions, footnotes)?,
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Pairs the footnote references of the document with their definitions.
+pub(super) fn resolve_footnotes(source: &str, document: &mut Document) -> Result<(), Diagnostic> {
+    let mut definitions = Vec::new();
+    take_definitions(&mut document.blocks, &mut definitions);
+
+    for (i, definition) in definitions.iter().enumerate() {
+        let message = if definitions[..i].iter().any(|d| d.id == definition.id) {
+            format!("footnote `{}` is defined more than once", definition.id)
+        } else if has_reference(&definition.text) {
+            format!("footnote `{}` contains another footnote", definition.id)
+        } else {
+            continue;
+        };
+        return Err(Diagnostic::new(source, definition.span.start, message));
+    }
+
+    let footnotes = &mut document.footnotes;
+    for_each_inlines(&mut document.blocks, &mut |inlines, span| {
+        resolve(inlines, &mut definitions, footnotes).map_err(|id| {
+            let reference = format!("[^{}]", id);
+            let offset = source[span.start..span.end].find(&reference).unwrap_or(0);
+
+            Diagnostic::new(source, span.start + offset, format!("footnote `{}` is not defined", id))
+        })
+    })?;
+
+    // an unused footnote is left out, which is harmless
+    for unused in definitions.iter().filter(|d| d.number.is_none()) {
+        document.warnings.push(Diagnostic::warning(
+            source, 
+            unused.span.start, 
+            format!("footnote `{}` is never referenced", unused.id)
+        ));
+    }
+    Ok(())
+}
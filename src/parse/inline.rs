@@ -0,0 +1,251 @@
+use super::util::take_until_match;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take, take_until, take_till1, take_while},
+    character::complete::alpha1,
+    combinator::{verify, recognize, not, map},
+    multi::{many0, many1_count},
+    sequence::{delimited, preceded, pair},
+};
+use nom::IResult;
+
+/// A span of text inside a block.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Emphasis(Vec<Inline>),
+    Strong(Vec<Inline>),
+    Code(String),
+    Math(String),
+    /// A link with its text and its destination.
+    Link(Vec<Inline>, String),
+    /// A destination written as `<https://...>`, shown as it is.
+    Url(String),
+    /// A `[^id]` reference, which the parser turns into a `Footnote`.
+    FootnoteReference(String),
+    /// A footnote with its number and its text.
+    Footnote(usize, Vec<Inline>),
+    /// A `@label` reference, with the number of what it points to once 
+    /// the document is resolved.
+    Reference(String, Option<String>),
+    /// Text in another format, written as `` `\newpage`{=latex} ``, with 
+    /// its format and its content. Only the compilers of that format 
+    /// keep it.
+    Raw(String, String),
+}
+
+// spans can't start or end with whitespace, so that `2 * 3 * 4`
+// stays as plain text
+fn is_span_content(s: &str) -> bool {
+    !s.is_empty() &&
+        !s.starts_with(char::is_whitespace) &&
+        !s.ends_with(char::is_whitespace)
+}
+
+// takes the content of a span until its closing delimiter, skipping
+// over the spans that may be nested inside of it
+fn span_content<'a, P>(nested: P, delimiter: &'static str)
+-> impl Fn(&'a str) -> IResult<&'a str, &'a str>
+    where P: Fn(&'a str) -> IResult<&'a str, Inline>
+{
+    move |input: &'a str|
+        verify(
+            recognize(
+                many1_count(
+                    alt((
+                        recognize(&nested),
+                        preceded(
+                            not(tag(delimiter)),
+                            take(1u8)
+                        )
+                    ))
+                )
+            ),
+            is_span_content
+        )(input)
+}
+
+fn parse_code(input: &str) -> IResult<&str, Inline> {
+    map(
+        delimited(
+            tag("`"),
+            verify(take_until("`"), |s: &str| !s.is_empty()),
+            tag("`")
+        ),
+        |s: &str| Inline::Code(s.to_string())
+    )(input)
+}
+
+fn parse_raw(input: &str) -> IResult<&str, Inline> {
+    map(
+        pair(
+            delimited(
+                tag("`"),
+                verify(take_until("`"), |s: &str| !s.is_empty()),
+                tag("`")
+            ),
+            delimited(tag("{="), alpha1, tag("}"))
+        ),
+        |(content, format): (&str, &str)| Inline::Raw(format.to_string(), content.to_string())
+    )(input)
+}
+
+fn parse_math(input: &str) -> IResult<&str, Inline> {
+    map(
+        delimited(
+            tag("$"),
+            verify(take_until("$"), is_span_content),
+            tag("$")
+        ),
+        |s: &str| Inline::Math(s.to_string())
+    )(input)
+}
+
+fn parse_link(input: &str) -> IResult<&str, Inline> {
+    map(
+        pair(
+            delimited(
+                tag("["),
+                span_content(alt((parse_code, parse_math)), "]"),
+                tag("]")
+            ),
+            delimited(
+                tag("("),
+                take_till1(|c: char| c == ')' || c.is_whitespace()),
+                tag(")")
+            )
+        ),
+        |(text, url): (&str, &str)| Inline::Link(parse_inlines(text), url.to_string())
+    )(input)
+}
+
+fn parse_footnote_reference(input: &str) -> IResult<&str, Inline> {
+    map(
+        delimited(
+            tag("[^"),
+            take_till1(|c: char| c == ']' || c.is_whitespace()),
+            tag("]")
+        ),
+        |id: &str| Inline::FootnoteReference(id.to_string())
+    )(input)
+}
+
+fn parse_reference(input: &str) -> IResult<&str, Inline> {
+    let (_, label) = preceded(
+        tag("@"),
+        recognize(pair(
+            alpha1,
+            take_while(|c: char| c.is_alphanumeric() || "-_:.".contains(c))
+        ))
+    )(input)?;
+    // so that `@label.` can end a sentence
+    let label = label.trim_end_matches(['.', ':']);
+
+    Ok((&input[label.len() + 1..], Inline::Reference(label.to_string(), None)))
+}
+
+fn parse_autolink(input: &str) -> IResult<&str, Inline> {
+    map(
+        delimited(
+            tag("<"),
+            recognize(pair(
+                alt((tag("https://"), tag("http://"), tag("mailto:"))),
+                take_till1(|c: char| c == '>' || c.is_whitespace())
+            )),
+            tag(">")
+        ),
+        |url: &str| Inline::Url(url.to_string())
+    )(input)
+}
+
+fn parse_strong(input: &str) -> IResult<&str, Inline> {
+    map(
+        delimited(
+            tag("**"),
+            span_content(alt((parse_code, parse_math, parse_emphasis)), "**"),
+            tag("**")
+        ),
+        |s: &str| Inline::Strong(parse_inlines(s))
+    )(input)
+}
+
+fn parse_emphasis(input: &str) -> IResult<&str, Inline> {
+    map(
+        delimited(
+            tag("*"),
+            span_content(alt((parse_code, parse_math, parse_strong)), "*"),
+            tag("*")
+        ),
+        |s: &str| Inline::Emphasis(parse_inlines(s))
+    )(input)
+}
+
+fn parse_span(input: &str) -> IResult<&str, Inline> {
+    alt((
+        parse_raw,
+        parse_code,
+        parse_math,
+        parse_footnote_reference,
+        parse_link,
+        parse_autolink,
+        parse_reference,
+        parse_strong,
+        parse_emphasis,
+    ))(input)
+}
+
+// any character that doesn't start a span is taken as text
+fn parse_text(input: &str) -> IResult<&str, Inline> {
+    map(
+        take_until_match(parse_span),
+        |s: &str| Inline::Text(s.to_string())
+    )(input)
+}
+
+/// Joins the text of inline spans, dropping their formatting.
+pub fn plain_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+
+    for inline in inlines {
+        match inline {
+            Inline::Text(t) | Inline::Code(t) | Inline::Math(t) | Inline::Url(t) => text.push_str(t),
+            Inline::Emphasis(i) | Inline::Strong(i) | Inline::Link(i, _) => text.push_str(&plain_text(i)),
+            Inline::FootnoteReference(_) | Inline::Footnote(..) | Inline::Raw(..) => (),
+            Inline::Reference(label, number) => match number {
+                Some(number) => text.push_str(number),
+                None => text.push_str(&format!("@{}", label)),
+            },
+        }
+    }
+    text
+}
+
+// an `@` right after a word, as in an email address, isn't a reference
+fn join_words(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut joined: Vec<Inline> = Vec::with_capacity(inlines.len());
+
+    for inline in inlines {
+        match (joined.last_mut(), inline) {
+            (Some(Inline::Text(text)), Inline::Reference(label, _)) 
+                if text.ends_with(char::is_alphanumeric) => 
+            {
+                text.push('@');
+                text.push_str(&label);
+            }
+            (Some(Inline::Text(text)), Inline::Text(next)) => text.push_str(&next),
+            (_, inline) => joined.push(inline),
+        }
+    }
+    joined
+}
+
+/// Parses the text of a block into inline spans.
+pub fn parse_inlines(input: &str) -> Vec<Inline> {
+    let r: IResult<&str, Vec<Inline>> = many0(
+        alt((
+            parse_span,
+            parse_text,
+        ))
+    )(input);
+    join_words(r.expect("parse_inlines cannot fail").1)
+}
@@ -0,0 +1,128 @@
+use super::{for_each_inlines, Block, Inline, Span, Spanned, Diagnostic, Document};
+
+/// Numbers the headings, figures and tables of a document in the order
+/// they appear, the way LaTeX does.
+#[derive(Debug, Clone, Default)]
+pub struct Numbering {
+    sections: Vec<usize>,
+    figures: usize,
+    tables: usize,
+}
+impl Numbering {
+    /// The number of the next block, if it gets one. Figures and tables
+    /// are only numbered when they have a caption or a label.
+    pub fn next(&mut self, block: &Block) -> Option<String> {
+        match block {
+            Block::Heading(level, ..) => {
+                let level = (*level).max(1);
+                self.sections.resize(level, 0);
+                self.sections[level - 1] += 1;
+
+                let numbers: Vec<String> = self.sections.iter().map(|n| n.to_string()).collect();
+                Some(numbers.join("."))
+            }
+            Block::Figure { caption, label, .. } if !caption.is_empty() || label.is_some() => {
+                self.figures += 1;
+                Some(self.figures.to_string())
+            }
+            Block::Table { caption, label, .. } if !caption.is_empty() || label.is_some() => {
+                self.tables += 1;
+                Some(self.tables.to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+fn label(block: &Block) -> Option<&String> {
+    match block {
+        Block::Heading(_, _, label) |
+        Block::Figure { label, .. } |
+        Block::Table { label, .. } => label.as_ref(),
+        _ => None,
+    }
+}
+
+// the labels of the blocks with their numbers, in the order of the document
+fn collect(blocks: &mut [Spanned<Block>], numbering: &mut Numbering, labels: &mut Vec<(String, String, Span)>) {
+    for block in blocks {
+        let number = numbering.next(&block.node);
+
+        if let (Some(label), Some(number)) = (label(&block.node), number) {
+            labels.push((label.clone(), number, block.span));
+        }
+        for children in block.node.children_mut() {
+            collect(children, numbering, labels);
+        }
+    }
+}
+
+// fills in the numbers of the references, returning the first label
+// with a `kind:` prefix that doesn't exist, the other `@name`s that
+// don't exist are put in `unknown`
+fn resolve(inlines: &mut [Inline], labels: &[(String, String, Span)], unknown: &mut Vec<String>) -> Result<(), String> {
+    for inline in inlines {
+        match inline {
+            Inline::Reference(label, number) => match labels.iter().find(|(l, _, _)| l == label) {
+                Some((_, found, _)) => *number = Some(found.clone()),
+                None if label.contains(':') => return Err(label.clone()),
+                // an `@name` in prose, such as a handle, or a typo
+                None => {
+                    unknown.push(label.clone());
+                    *inline = Inline::Text(format!("@{}", label));
+                }
+            },
+            Inline::Emphasis(i) | Inline::Strong(i) | Inline::Link(i, _) => resolve(i, labels, unknown)?,
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every `@kind:label` reference points to a labeled block,
+/// giving it the number of that block. Other `@name`s that aren't labels
+/// are kept as text, with a warning.
+pub(super) fn resolve_labels(source: &str, document: &mut Document) -> Result<(), Diagnostic> {
+    let mut labels = Vec::new();
+    collect(&mut document.blocks, &mut Numbering::default(), &mut labels);
+
+    for (i, (label, _, span)) in labels.iter().enumerate() {
+        if labels[..i].iter().any(|(l, _, _)| l == label) {
+            let offset = source[span.start..span.end].find(&format!("#{}", label)).unwrap_or(0);
+
+            return Err(Diagnostic::new(
+                source, 
+                span.start + offset, 
+                format!("label `{}` is defined more than once", label)
+            ));
+        }
+    }
+
+    let warnings = &mut document.warnings;
+    for_each_inlines(&mut document.blocks, &mut |inlines, span| {
+        // the first `@label` that isn't inside another word
+        let position = |label: &str| {
+            let reference = format!("@{}", label);
+            let text = &source[span.start..span.end];
+            let offset = text.match_indices(&reference)
+                .map(|(i, _)| i)
+                .find(|&i| !text[..i].ends_with(char::is_alphanumeric))
+                .unwrap_or(0);
+            span.start + offset
+        };
+        let mut unknown = Vec::new();
+
+        resolve(inlines, &labels, &mut unknown).map_err(|label| {
+            Diagnostic::new(source, position(&label), format!("reference to undefined label `{}`", label))
+        })?;
+
+        for name in unknown {
+            warnings.push(Diagnostic::warning(
+                source,
+                position(&name),
+                format!("`@{}` isn't a label, so it's kept as text", name)
+            ));
+        }
+        Ok(())
+    })
+}
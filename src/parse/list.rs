@@ -1,59 +1,144 @@
-use super::format_text;
+use super::{parse_nested, is_blank, Block, Span, Spanned, Error};
 use nom::{
-    branch::alt,
-    bytes::complete::{tag, take},
-    combinator::{verify, recognize, opt, not, map},
-    multi::{many1, count},
-    sequence::{pair, terminated, preceded},
-    character::complete::line_ending,
+    combinator::{verify, recognize, opt},
+    multi::many1,
+    sequence::terminated,
+    character::complete::{line_ending, space1},
+    Offset,
 };
-use nom::IResult;
+use super::IResult;
+use std::str::FromStr;
 
 pub use super::token::{ListToken, TokenEnumerator, TokenWrapper};
 use super::token::parse_item_token;
 
+/// How nested list items are indented.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Indentation {
+    Tabs,
+    Spaces(usize),
+    /// Detected from the first nested item of the document.
+    #[default]
+    Auto,
+}
+impl FromStr for Indentation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tabs" | "tab" => Ok(Indentation::Tabs),
+            "auto" => Ok(Indentation::Auto),
+            _ => match s.parse() {
+                Ok(n) if n > 0 => Ok(Indentation::Spaces(n)),
+                _ => Err(format!("expected `tabs`, `auto` or a number of spaces, found `{}`", s)),
+            }
+        }
+    }
+}
+impl Indentation {
+    /// Finds the indentation of the first nested list item.
+    /// Defaults to tabs if there are no nested items.
+    pub fn detect(input: &str) -> Indentation {
+        let mut fenced = false;
+
+        for line in input.lines() {
+            let content = line.trim_start_matches([' ', '\t']);
+
+            // code blocks may have anything inside them, even nested ones
+            if content.starts_with("```") {
+                fenced = !fenced;
+                continue;
+            }
+
+            if fenced || content.len() == line.len() {
+                continue;
+            }
+            if parse_item_start(content).is_ok() {
+                return match line.starts_with('\t') {
+                    true => Indentation::Tabs,
+                    false => Indentation::Spaces(line.len() - line.trim_start_matches(' ').len()),
+                };
+            }
+        }
+        Indentation::Tabs
+    }
+    // the whitespace before a nested item
+    fn unit(&self) -> String {
+        match self {
+            Indentation::Spaces(n) => " ".repeat(*n),
+            _ => String::from("\t"),
+        }
+    }
+    fn describe(&self) -> String {
+        match self {
+            Indentation::Spaces(1) => String::from("1 space"),
+            Indentation::Spaces(n) => format!("{} spaces", n),
+            _ => String::from("tabs"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct List {
     pub vec: Vec<ListItem>,
-    pub token: ListToken
+    pub token: ListToken,
+    /// The value of the first item's enumerator.
+    pub start: usize,
+    pub span: Span,
+}
+impl List {
+    pub(super) fn map_spans(&mut self, f: &dyn Fn(usize) -> usize) {
+        self.span = self.span.map(f);
+
+        for item in &mut self.vec {
+            item.span = item.span.map(f);
+
+            for block in &mut item.blocks {
+                block.span = block.span.map(f);
+                block.node.map_spans(f);
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ListItem {
-    pub text: String, 
-    pub list: Option<List>
+    /// The content of the item: its first line along with the lines
+    /// indented below it.
+    pub blocks: Vec<Spanned<Block>>,
+    pub span: Span,
 }
 
-fn parse_item_start(indent: usize) -> impl Fn(&str) -> IResult<&str, ListToken> {
-    move |input: &str|
-        map(
-            pair(
-                count(tag("\t"), indent),
-                parse_item_token,
-            ),
-            |(_, token)| token
-        )(input)
+/// Parses the token that starts a list item.
+pub(super) fn parse_item_start(input: &str) -> IResult<&str, (ListToken, usize)> {
+    // the token must be followed by whitespace, otherwise
+    // `*emphasized*` text would start a list
+    terminated(parse_item_token, space1)(input)
 }
 
-fn parse_item_start_and_enforce(indent: usize, enforced: &'_ ListToken) -> impl Fn(&str) -> IResult<&str, ListToken> + '_ {
+fn parse_item_start_and_enforce(enforced: &'_ ListToken) 
+-> impl Fn(&str) -> IResult<&str, (ListToken, usize)> + '_ 
+{
     move |input: &str|
         verify(
-            parse_item_start(indent),
-            |token| {
+            parse_item_start,
+            |(token, _)| {
                 if token == enforced {
                     return true;
                 } 
                 // this enables roman numerals to also qualify
-                // as alphabetic tokens
+                // as alphabetic tokens, and the other way around,
+                // since a list may start on a letter such as `c`
                 if let Some(a) = &enforced.enumerator {
                     if let Some(b) = &token.enumerator {
                         use TokenEnumerator::*;
 
-                        if let Alphabetical(alpha_upper) = &a {
-                            if let Roman(roman_upper) = &b {
-                                // this part checks if they have the same case
-                                return *alpha_upper == *roman_upper;
-                            }
+                        match (a, b) {
+                            // this part checks if they have the same case
+                            (Alphabetical(alpha_upper), Roman(roman_upper)) |
+                            (Roman(roman_upper), Alphabetical(alpha_upper)) => 
+                                return alpha_upper == roman_upper,
+                            _ => (),
                         }
                     }
                 }
@@ -62,88 +147,146 @@ fn parse_item_start_and_enforce(indent: usize, enforced: &'_ ListToken) -> impl
         )(input)
 }
 
-fn parse_item(indent: usize, token: &ListToken) -> impl Fn(&str) -> IResult<&str, ListItem> + '_ {
-    let item_start = parse_item_start_and_enforce(indent, token);
-    let next_item_start = parse_item_start(indent + 1);
+// the token as it was written
+fn token_text(input: &str) -> &str {
+    recognize(parse_item_token)(input)
+        .map(|(_, text)| text)
+        .unwrap_or(input)
+}
 
-    move |input: &str|
-        map(
-            terminated(
-                pair(
-                    &item_start,
-                    map(
-                        many1(
-                            preceded(
-                                not(pair(
-                                    line_ending,
-                                    alt((
-                                        recognize(line_ending),
-                                        recognize(&item_start),
-                                    ))
-                                )),
-                                take(1u8)
-                            )
-                        ),
-                        |s: Vec<&str>| s.join("").trim().to_string()
-                    ),
-                ),
-                opt(line_ending)
-            ),
-            |(_, content)| {
-                let mut list = None;
-
-                let text;
-                // checks for sublists
-                for (i, _) in content.char_indices() {
-                    let s = &content[i..];
-
-                    if let Ok(_) = next_item_start(s) {
-                        if let Ok(result) = parse_list(indent + 1)(s) {
-                            let consumed = content[..i].trim();
-                            list = Some((consumed, result.1));
-                            break;
-                        }
-                    }
-                }
-                if let Some((consumed, _)) = list {
-                    // -1 because of the newline
-                    text = format_text(&consumed);
-                } else {
-                    text = format_text(&content.trim());
-                }
+// the lines of the input, paired with their offsets
+fn lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+
+    input.split('\n')
+        .map(move |line| {
+            let start = offset;
+            offset += line.len() + 1;
+            (start, line)
+        })
+}
 
-                ListItem {
-                    text,
-                    list: list.map(|l| l.1)
+fn parse_item<'a: 'b, 'b>(source: &'a str, indentation: Indentation, token: &'b ListToken) 
+-> impl Fn(&'a str) -> IResult<&'a str, ListItem> + 'b 
+{
+    let item_start = parse_item_start_and_enforce(token);
+    let unit = indentation.unit();
+
+    move |input: &'a str| {
+        let (first, _) = item_start(input)?;
+        // only the lines up to the end of the item are looked at
+        let mut all = lines(input).peekable();
+        let (_, first_line) = all.next().expect("split always yields a line");
+
+        // the item goes on until a blank line or the start of another
+        // item, but lines indented after a blank line continue it
+        let skipped = input.offset(first);
+        let mut taken = vec![(skipped, &first_line[skipped..])];
+        let mut blanks = Vec::new();
+        let mut end = first_line.len() + 1;
+
+        while let Some(&(start, line)) = all.peek() {
+            if is_blank(line) {
+                blanks.push((start, line));
+            } else if !blanks.is_empty() {
+                if !line.starts_with(unit.as_str()) {
+                    break;
                 }
+                taken.append(&mut blanks);
+                taken.push((start, line));
+                end = start + line.len() + 1;
+            } else if parse_item_start(line).is_ok() {
+                break;
+            } else {
+                taken.push((start, line));
+                end = start + line.len() + 1;
             }
-        )(input)
+            all.next();
+        }
+        let rest = &input[end.min(input.len())..];
+
+        // the content is parsed on its own with one level of indentation removed
+        let base = source.offset(input);
+        let content: Vec<(usize, &str)> = taken.into_iter()
+            .enumerate()
+            .map(|(n, (start, line))| match line.strip_prefix(unit.as_str()) {
+                Some(stripped) if n > 0 => (base + start + unit.len(), stripped),
+                _ => (base + start, line),
+            })
+            .collect();
+        let blocks = parse_nested(source, &content, indentation)?;
+
+        Ok((rest, ListItem {
+            blocks,
+            span: Span::consumed(source, input, rest),
+        }))
+    }
 }
 
-pub fn parse_list(indent: usize) -> impl Fn(&str) -> IResult<&str, List> + 'static {
-    move |input: &str| {
-        // this works, but there must be a better way
-        let mut token = ListToken::bullet();
+// checks that every item of the list is indented with whole units 
+// of the document's indentation
+fn check_indentation(indentation: Indentation, list: &str) -> Result<(), nom::Err<Error<&str>>> {
+    let mut fenced = false;
+
+    for line in list.lines() {
+        let content = line.trim_start_matches([' ', '\t']);
+        let whitespace = &line[..line.len() - content.len()];
 
-        if let Ok((_, result)) = parse_item_start(indent)(input) {
-            token = result;
+        // code blocks inside items may have anything inside them
+        if content.starts_with("```") {
+            fenced = !fenced;
+        }
+        if fenced || whitespace.is_empty() || parse_item_start(content).is_err() {
+            continue;
+        }
+        let valid = match indentation {
+            Indentation::Spaces(n) => 
+                whitespace.chars().all(|c| c == ' ') && whitespace.len() % n == 0,
+            _ => whitespace.chars().all(|c| c == '\t'),
+        };
+        if !valid {
+            let found = match (whitespace.contains(' '), whitespace.contains('\t')) {
+                (true, true) => String::from("a mix of tabs and spaces"),
+                (true, false) => Indentation::Spaces(whitespace.len()).describe(),
+                _ => String::from("tabs"),
+            };
+            return Err(Error::failure(line, format!(
+                "list item is indented with {}, but the document is indented with {}",
+                found,
+                indentation.describe(),
+            )));
         }
-        let clone = token.clone();
-
-        map(
-            terminated(
-                many1(
-                    parse_item(indent, &token.clone())
-                ),
-                opt(line_ending)
-            ),
-            move |items| {
-                List {
-                    vec: items,
-                    token: clone.clone()
-                }
-            }
-        )(input)
     }
+    Ok(())
 }
 
+pub fn parse_list<'a>(source: &'a str, indentation: Indentation) 
+-> impl Fn(&'a str) -> IResult<&'a str, List> 
+{
+    move |input: &'a str| {
+        let (_, (token, start)) = parse_item_start(input)?;
+
+        let (rest, items) = many1(
+            parse_item(source, indentation, &token)
+        )(input)?;
+
+        check_indentation(indentation, &input[..input.offset(rest)])?;
+
+        // the list was interrupted by an item with another token
+        if parse_item_start(rest).is_ok() {
+            return Err(Error::failure(rest, format!(
+                "list item uses `{}` but list started with `{}`",
+                token_text(rest),
+                token_text(input),
+            )));
+        }
+        let (rest, _) = opt(line_ending)(rest)?;
+
+        Ok((rest, List {
+            vec: items,
+            token,
+            start,
+            span: Span::consumed(source, input, rest),
+        }))
+    }
+}
@@ -0,0 +1,176 @@
+use super::{take_until_match, Error, IResult};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::map,
+    multi::many0_count,
+    sequence::{preceded, terminated, pair},
+    character::complete::{line_ending, space0},
+};
+
+/// Information about the document, written in its front matter.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub language: Option<String>,
+    /// The LaTeX document class.
+    pub class: Option<String>,
+    pub r#abstract: Option<String>,
+    /// Whether a table of contents goes after the title.
+    pub toc: bool,
+}
+impl Metadata {
+    fn field(&mut self, key: &str) -> Option<&mut Option<String>> {
+        match key {
+            "title" => Some(&mut self.title),
+            "author" => Some(&mut self.author),
+            "date" => Some(&mut self.date),
+            "lang" | "language" => Some(&mut self.language),
+            "class" | "documentclass" => Some(&mut self.class),
+            "abstract" => Some(&mut self.r#abstract),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Yaml,
+    Toml,
+}
+
+fn unquote(value: &str, format: Format) -> String {
+    let quoted = |q: char| value.len() >= 2 && value.starts_with(q) && value.ends_with(q);
+
+    if quoted('"') {
+        let inner = &value[1..value.len() - 1];
+        match format {
+            Format::Toml => inner
+                .replace("\\\"", "\"")
+                .replace("\\n", "\n")
+                .replace("\\\\", "\\"),
+            Format::Yaml => inner.replace("\\\"", "\""),
+        }
+    } else if quoted('\'') {
+        let inner = &value[1..value.len() - 1];
+        match format {
+            Format::Toml => inner.to_string(),
+            Format::Yaml => inner.replace("''", "'"),
+        }
+    } else {
+        value.to_string()
+    }
+}
+
+// parses the `key: value` (YAML) or `key = value` (TOML) lines of the
+// front matter, `lines` being a slice of the source
+fn parse_entries(lines: &str, format: Format) -> Result<Metadata, nom::Err<Error<&str>>> {
+    let separator = match format {
+        Format::Yaml => ':',
+        Format::Toml => '=',
+    };
+    let mut metadata = Metadata::default();
+    let mut entries = lines.lines().peekable();
+
+    while let Some(line) = entries.next() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match trimmed.find(separator) {
+            Some(i) => (trimmed[..i].trim(), trimmed[i + 1..].trim()),
+            None => return Err(Error::failure(
+                trimmed,
+                format!("expected `key{} value` in the front matter", separator)
+            )),
+        };
+        let mut value = unquote(value, format);
+
+        // YAML values may continue on the following indented lines,
+        // `|` keeps their line breaks and `>` folds them
+        if let Format::Yaml = format {
+            let block = value.trim_end_matches(['-', '+']);
+            let newline = block == "|";
+
+            if newline || block == ">" {
+                value.clear();
+            }
+            while let Some(next) = entries.peek() {
+                if !next.starts_with([' ', '\t']) {
+                    break;
+                }
+                if !value.is_empty() {
+                    value.push(if newline { '\n' } else { ' ' });
+                }
+                value.push_str(next.trim());
+                entries.next();
+            }
+        }
+
+        if key == "toc" {
+            metadata.toc = match value.as_str() {
+                "true" => true,
+                "false" => false,
+                _ => return Err(Error::failure(
+                    trimmed,
+                    format!("expected `true` or `false` for `toc`, found `{}`", value)
+                )),
+            };
+            continue;
+        }
+        // both go into the preamble as they are
+        let name = matches!(key, "class" | "documentclass" | "lang" | "language");
+
+        if name && (value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')) {
+            return Err(Error::failure(
+                trimmed,
+                format!("expected letters, digits and `-` for `{}`, found `{}`", key, value)
+            ));
+        }
+        match metadata.field(key) {
+            Some(field) => *field = Some(value),
+            None => return Err(Error::failure(
+                trimmed,
+                format!("unknown front matter key `{}`", key)
+            )),
+        }
+    }
+    Ok(metadata)
+}
+
+fn delimiter(fence: &'static str) -> impl Fn(&str) -> IResult<&str, &str> {
+    move |input: &str| terminated(tag(fence), pair(space0, line_ending))(input)
+}
+
+fn front_matter(open: &'static str, close: &'static str) -> impl Fn(&str) -> IResult<&str, &str> {
+    move |input: &str| {
+        let closing = preceded(line_ending, terminated(tag(close), space0));
+        let parser = terminated(
+            preceded(
+                delimiter(open),
+                alt((
+                    // the closing delimiter right after the opening one
+                    map(terminated(tag(close), space0), |_| ""),
+                    terminated(take_until_match(&closing), &closing),
+                ))
+            ),
+            many0_count(line_ending)
+        );
+        parser(input)
+    }
+}
+
+/// Parses the front matter at the start of the document: a YAML block
+/// delimited by `---` or a TOML block delimited by `+++`.
+pub fn parse_metadata(input: &str) -> IResult<&str, Metadata> {
+    if let Ok((rest, lines)) = front_matter("---", "---")(input) {
+        return Ok((rest, parse_entries(lines, Format::Yaml)?));
+    }
+    if let Ok((rest, lines)) = front_matter("+++", "+++")(input) {
+        return Ok((rest, parse_entries(lines, Format::Toml)?));
+    }
+    Ok((input, Metadata::default()))
+}
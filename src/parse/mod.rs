@@ -1,23 +1,42 @@
 mod list;
 mod util;
 mod token;
+mod inline;
+mod code;
+mod span;
+mod error;
+mod metadata;
+mod table;
+mod attributes;
+mod footnote;
+mod label;
+mod outline;
 pub use util::*;
 pub use list::*;
+pub use inline::*;
+pub use code::*;
+pub use span::*;
+pub use error::Diagnostic;
+pub use metadata::*;
+pub use table::*;
+pub use attributes::*;
+pub use label::Numbering;
+pub use outline::OutlineEntry;
+use error::{IResult, Error};
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take},
-    combinator::{recognize, opt, map},
-    multi::{many0, many1, many1_count},
-    sequence::{terminated, pair},
-    character::complete::line_ending,
+    bytes::complete::{tag, take, take_until, take_till1},
+    combinator::{recognize, opt, map, peek},
+    multi::{many0, many0_count, many1, many1_count},
+    sequence::{delimited, terminated, pair},
+    character::complete::{line_ending, space0},
+    Offset,
 };
-use nom::IResult;
-use nom::error::ErrorKind;
 
 /// Replaces all consecutive line endings and tabs with a single space
 pub fn format_text(s: &str) -> String {
-    let r: IResult<&str, String> = map(
+    let r: nom::IResult<&str, String> = map(
         many0(
             alt((
                 map(
@@ -36,9 +55,95 @@ pub fn format_text(s: &str) -> String {
 
 #[derive(Debug, Clone)]
 pub enum Block {
-    Heading(usize, String),
-    Paragraph(String),
+    /// The level, the title and an optional label.
+    Heading(usize, Vec<Inline>, Option<String>),
+    Paragraph(Vec<Inline>),
     List(List), // contains both ordered and unordered
+    CodeBlock {
+        lang: Option<String>,
+        code: String,
+    },
+    Math(String), // display math, kept as written
+    Quote(Vec<Spanned<Block>>),
+    Table {
+        header: Row,
+        align: Vec<Alignment>,
+        rows: Vec<Row>,
+        caption: Vec<Inline>,
+        label: Option<String>,
+    },
+    Figure {
+        path: String,
+        caption: Vec<Inline>,
+        label: Option<String>,
+        /// As written, a length such as `4cm` or a percentage like `50%`.
+        width: Option<String>,
+        height: Option<String>,
+    },
+    /// The text of a footnote, `[^id]: text`. These are taken out of
+    /// the document once their references are resolved.
+    FootnoteDefinition(String, Vec<Inline>),
+    /// A `[TOC]` line, where the table of contents goes.
+    TableOfContents,
+    /// A fenced block of another format, ` ```{=latex} `. Only the 
+    /// compilers of that format keep it.
+    Raw {
+        format: String,
+        content: String,
+    },
+}
+impl Block {
+    // moves the spans nested inside the block
+    fn map_spans(&mut self, f: &dyn Fn(usize) -> usize) {
+        match self {
+            Block::List(list) => list.map_spans(f),
+            Block::Quote(blocks) => for block in blocks {
+                block.span = block.span.map(f);
+                block.node.map_spans(f);
+            },
+            _ => (),
+        }
+    }
+    // the inline text of the block, without the one of nested blocks
+    fn inlines_mut(&mut self) -> Vec<&mut Vec<Inline>> {
+        match self {
+            Block::Heading(_, inlines, _) | 
+            Block::Paragraph(inlines) | 
+            Block::FootnoteDefinition(_, inlines) |
+            Block::Figure { caption: inlines, .. } => vec![inlines],
+            Block::Table { header, rows, caption, .. } => header.iter_mut()
+                .chain(rows.iter_mut().flatten())
+                .chain(std::iter::once(caption))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    // the blocks nested inside this one
+    fn children_mut(&mut self) -> Vec<&mut Vec<Spanned<Block>>> {
+        match self {
+            Block::List(list) => list.vec.iter_mut().map(|item| &mut item.blocks).collect(),
+            Block::Quote(blocks) => vec![blocks],
+            _ => Vec::new(),
+        }
+    }
+}
+
+// calls `f` with the inline text of every block, in the order of the
+// document, along with the span of the block it belongs to
+fn for_each_inlines<E, F>(blocks: &mut [Spanned<Block>], f: &mut F) -> Result<(), E> 
+    where F: FnMut(&mut Vec<Inline>, Span) -> Result<(), E>
+{
+    for block in blocks {
+        let span = block.span;
+
+        for inlines in block.node.inlines_mut() {
+            f(inlines, span)?;
+        }
+        for children in block.node.children_mut() {
+            for_each_inlines(children, f)?;
+        }
+    }
+    Ok(())
 }
 
 fn end(input: &str) -> IResult<&str, &str> {
@@ -64,41 +169,300 @@ fn parse_block_heading(input: &str) -> IResult<&str, Block> {
             ),
             block_text
         ),
-        |(level, s)| Block::Heading(level, s)
+        |(level, s)| {
+            let (title, label) = split_label(&s);
+            Block::Heading(level, parse_inlines(&format_text(title.trim_end())), label)
+        }
     )(input)
 }
 
+// `[^id]:`, the start of a footnote definition
+fn footnote_start(input: &str) -> IResult<&str, &str> {
+    delimited(
+        tag("[^"),
+        take_till1(|c: char| c == ']' || c.is_whitespace()),
+        tag("]:")
+    )(input)
+}
+
+// a list, a quote or a footnote may start right below a paragraph
+fn paragraph_end(input: &str) -> IResult<&str, &str> {
+    alt((
+        end,
+        recognize(pair(line_ending, peek(parse_item_start))),
+        recognize(pair(line_ending, peek(tag(">")))),
+        recognize(pair(line_ending, peek(footnote_start))),
+    ))(input)
+}
+
 fn parse_block_paragraph(input: &str) -> IResult<&str, Block> {
     map(
-        block_text,
-        |s| Block::Paragraph(format_text(&s))
+        terminated(
+            take_until_match(paragraph_end),
+            opt(alt((end, line_ending)))
+        ),
+        |s| Block::Paragraph(parse_inlines(&format_text(s)))
     )(input)
 }
 
-fn parse_block_list(input: &str) -> IResult<&str, Block> {
+fn parse_block_footnote(input: &str) -> IResult<&str, Block> {
     map(
-        parse_list(0),
-        |l| Block::List(l)
+        pair(
+            terminated(footnote_start, space0),
+            terminated(
+                take_until_match(paragraph_end),
+                opt(alt((end, line_ending)))
+            )
+        ),
+        |(id, text)| Block::FootnoteDefinition(id.to_string(), parse_inlines(&format_text(text.trim_end())))
     )(input)
 }
 
-fn parse_block(input: &str) -> IResult<&str, Block> {
-    alt((
-        parse_block_heading,
-        parse_block_list,
-        parse_block_paragraph,
-    ))(input)
+fn parse_block_list<'a>(source: &'a str, indentation: Indentation) -> impl Fn(&'a str) -> IResult<&'a str, Block> {
+    move |input: &'a str|
+        map(
+            parse_list(source, indentation),
+            Block::List
+        )(input)
+}
+
+// `{=latex}`, the info string of a raw block
+fn raw_format(info: &str) -> Option<&str> {
+    info.strip_prefix("{=")?
+        .strip_suffix('}')
+        .filter(|format| !format.is_empty() && format.chars().all(char::is_alphabetic))
+}
+
+fn parse_block_code(input: &str) -> IResult<&str, Block> {
+    map(
+        parse_fenced,
+        |(lang, code)| match lang.and_then(raw_format) {
+            Some(format) => Block::Raw {
+                format: format.to_string(),
+                content: code,
+            },
+            None => Block::CodeBlock { 
+                lang: lang.map(|l| l.to_string()), 
+                code 
+            },
+        }
+    )(input)
+}
+
+// a number with a unit that both LaTeX and CSS know, or a percentage
+fn is_length(value: &str) -> bool {
+    let number = value.trim_end_matches(char::is_alphabetic).trim_end_matches('%');
+    let unit = &value[number.len()..];
+    let mut parts = number.splitn(2, '.');
+    let digits = |part: Option<&str>| part.unwrap_or("").chars().all(|c| c.is_ascii_digit());
+
+    !number.is_empty() && number != "."
+        && digits(parts.next()) && digits(parts.next())
+        && ["%", "pt", "pc", "mm", "cm", "in", "em", "ex"].contains(&unit)
+}
+
+// an image alone in its block, `![caption](path){width=50%}`
+fn parse_block_figure(input: &str) -> IResult<&str, Block> {
+    let (after, (caption, path)) = pair(
+        delimited(tag("!["), take_until("]"), tag("]")),
+        delimited(tag("("), take_till1(|c| c == ')' || c == '\n'), tag(")")),
+    )(input)?;
+    let (rest, attributes) = terminated(
+        opt(parse_attributes),
+        pair(space0, alt((recognize(many1(line_ending)), eof)))
+    )(after)?;
+    // LaTeX can't read these in a path, not even escaped
+    if let Some(i) = path.find(|c| "%#{}".contains(c)) {
+        return Err(Error::failure(
+            &path[i..],
+            String::from("figure paths can't contain `%`, `#`, `{` or `}`")
+        ));
+    }
+    let attributes = attributes.unwrap_or_default();
+    let mut width = None;
+    let mut height = None;
+
+    for (key, value) in attributes.pairs {
+        match key.as_str() {
+            "width" | "height" if !is_length(&value) => return Err(Error::failure(
+                &after[after.find(&format!("{}={}", key, value)).map_or(0, |i| i + key.len() + 1)..],
+                format!("figure {} `{}` isn't a length like `4cm` or `50%`", key, value)
+            )),
+            "width" => width = Some(value),
+            "height" => height = Some(value),
+            _ => return Err(Error::failure(
+                &after[after.find(&key).unwrap_or(0)..],
+                format!("unknown figure attribute `{}`", key)
+            )),
+        }
+    }
+
+    Ok((rest, Block::Figure {
+        path: path.trim().to_string(),
+        caption: parse_inlines(&format_text(caption)),
+        label: attributes.id,
+        width,
+        height,
+    }))
+}
+
+fn parse_block_toc(input: &str) -> IResult<&str, Block> {
+    map(
+        terminated(
+            tag("[TOC]"),
+            pair(space0, alt((recognize(many1(line_ending)), eof)))
+        ),
+        |_| Block::TableOfContents
+    )(input)
+}
+
+fn parse_block_math(input: &str) -> IResult<&str, Block> {
+    map(
+        terminated(
+            delimited(
+                tag("$$"),
+                take_until("$$"),
+                tag("$$")
+            ),
+            pair(space0, alt((recognize(many1(line_ending)), eof)))
+        ),
+        |s: &str| Block::Math(s.trim().to_string())
+    )(input)
+}
+
+// every line of the quote starts with `>`, which may be followed by a space
+fn parse_block_quote<'a>(source: &'a str, indentation: Indentation) -> impl Fn(&'a str) -> IResult<&'a str, Block> {
+    move |input: &'a str| {
+        tag(">")(input)?;
+        let base = source.offset(input);
+        let mut lines = Vec::new();
+        let mut offset = 0;
+
+        for line in input.split('\n') {
+            let content = match line.strip_prefix('>') {
+                Some(content) => content,
+                None => break,
+            };
+            let content = content.strip_prefix(' ').unwrap_or(content);
+            lines.push((base + offset + line.len() - content.len(), content));
+            offset += line.len() + 1;
+        }
+        let rest = &input[offset.min(input.len())..];
+        let (rest, _) = many0_count(line_ending)(rest)?;
+
+        Ok((rest, Block::Quote(parse_nested(source, &lines, indentation)?)))
+    }
+}
+
+fn parse_block<'a>(source: &'a str, indentation: Indentation) -> impl Fn(&'a str) -> IResult<&'a str, Spanned<Block>> {
+    move |input: &'a str|
+        spanned(
+            source,
+            alt((
+                parse_block_code,
+                parse_block_math,
+                parse_block_heading,
+                parse_block_list(source, indentation),
+                parse_block_quote(source, indentation),
+                parse_table,
+                parse_block_figure,
+                parse_block_footnote,
+                parse_block_toc,
+                parse_block_paragraph,
+            ))
+        )(input)
+}
+
+fn parse_blocks<'a>(source: &'a str, indentation: Indentation) -> impl Fn(&'a str) -> IResult<&'a str, Vec<Spanned<Block>>> {
+    move |input: &'a str| many0(parse_block(source, indentation))(input)
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+// parses the blocks nested inside another block, out of lines of `source`
+// paired with their offsets, so that spans and errors point at the source
+fn parse_nested<'a>(source: &'a str, lines: &[(usize, &str)], indentation: Indentation) 
+-> Result<Vec<Spanned<Block>>, nom::Err<Error<&'a str>>> 
+{
+    let mut text = String::new();
+    let mut origins = Vec::new();
+
+    for (n, (start, line)) in lines.iter().enumerate() {
+        if n > 0 {
+            text.push('\n');
+        }
+        if is_blank(line) {
+            continue;
+        }
+        origins.push((text.len(), *start));
+        text.push_str(line);
+    }
+    let origin = |offset: usize| {
+        match origins.iter().rev().find(|(at, _)| *at <= offset) {
+            Some((at, from)) => from + offset - at,
+            None => lines.first().map(|(start, _)| *start).unwrap_or(0),
+        }
+    };
+
+    let (_, mut blocks) = parse_blocks(&text, indentation)(&text)
+        .map_err(|e| e.map(|e| Error {
+            input: &source[origin(text.offset(e.input))..],
+            kind: e.kind,
+            message: e.message,
+        }))?;
+
+    for block in &mut blocks {
+        block.span = block.span.map(origin);
+        block.node.map_spans(&origin);
+    }
+    Ok(blocks)
 }
 
 /// An Aldoc document abstraction.
+#[derive(Debug, Clone)]
 pub struct Document {
-    pub blocks: Vec<Block>
+    pub metadata: Metadata,
+    pub blocks: Vec<Spanned<Block>>,
+    /// The text of the footnotes, the one numbered `n` being at `n - 1`.
+    pub footnotes: Vec<Vec<Inline>>,
+    /// Problems found while parsing that don't make it fail.
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Settings for the parser.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub indentation: Indentation,
 }
 
 /// Parses raw Aldoc text into a document abstraction.
-pub fn parse(input: &str) -> Result<Document, nom::Err<(&str, ErrorKind)>> {
-    many0(
-        parse_block
-    )(input)
-        .map(|(_, blocks)| Document { blocks })
+pub fn parse(input: &str) -> Result<Document, Diagnostic> {
+    parse_with(input, &ParseOptions::default())
+}
+
+/// Parses raw Aldoc text into a document abstraction, with the given settings.
+pub fn parse_with(input: &str, options: &ParseOptions) -> Result<Document, Diagnostic> {
+    let (body, metadata) = parse_metadata(input)
+        .map_err(|e| Diagnostic::from_error(input, e))?;
+
+    let indentation = match options.indentation {
+        Indentation::Auto => Indentation::detect(body),
+        i => i,
+    };
+
+    let (_, blocks) = parse_blocks(input, indentation)(body)
+        .map_err(|e| Diagnostic::from_error(input, e))?;
+
+    let mut document = Document {
+        metadata,
+        blocks,
+        footnotes: Vec::new(),
+        warnings: Vec::new(),
+    };
+    // labels go first, so that references inside footnotes are resolved
+    label::resolve_labels(input, &mut document)?;
+    footnote::resolve_footnotes(input, &mut document)?;
+    Ok(document)
 }
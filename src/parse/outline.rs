@@ -0,0 +1,58 @@
+use super::{Block, Inline, Numbering, Span, Spanned, Document};
+
+/// A heading of the document along with the headings below it.
+#[derive(Debug, Clone)]
+pub struct OutlineEntry {
+    pub level: usize,
+    pub title: Vec<Inline>,
+    /// The number of the section, such as `2.1`.
+    pub number: String,
+    pub label: Option<String>,
+    pub span: Span,
+    pub children: Vec<OutlineEntry>,
+}
+
+// the headings of the document, in order
+fn headings(blocks: &[Spanned<Block>], numbering: &mut Numbering, found: &mut Vec<OutlineEntry>) {
+    for block in blocks {
+        let number = numbering.next(block);
+
+        match &block.node {
+            Block::Heading(level, title, label) => found.push(OutlineEntry {
+                level: *level,
+                title: title.clone(),
+                number: number.unwrap_or_default(),
+                label: label.clone(),
+                span: block.span,
+                children: Vec::new(),
+            }),
+            Block::List(list) => for item in &list.vec {
+                headings(&item.blocks, numbering, found);
+            },
+            Block::Quote(blocks) => headings(blocks, numbering, found),
+            _ => (),
+        }
+    }
+}
+
+// puts each heading below the last one with a lower level
+fn insert(entries: &mut Vec<OutlineEntry>, entry: OutlineEntry) {
+    match entries.last_mut() {
+        Some(parent) if parent.level < entry.level => insert(&mut parent.children, entry),
+        _ => entries.push(entry),
+    }
+}
+
+impl Document {
+    /// The headings of the document as a tree.
+    pub fn outline(&self) -> Vec<OutlineEntry> {
+        let mut found = Vec::new();
+        headings(&self.blocks, &mut Numbering::default(), &mut found);
+
+        let mut outline = Vec::new();
+        for entry in found {
+            insert(&mut outline, entry);
+        }
+        outline
+    }
+}
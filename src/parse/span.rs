@@ -0,0 +1,80 @@
+use super::IResult;
+use nom::Offset;
+use std::ops::Deref;
+
+/// A region of the source text, as byte offsets.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+impl Span {
+    /// The span of `input` consumed by a parser that left `rest`, both being
+    /// slices of `source`. Trailing whitespace isn't included.
+    pub fn consumed(source: &str, input: &str, rest: &str) -> Span {
+        let consumed = &input[..input.offset(rest)];
+        let start = source.offset(input);
+
+        Span {
+            start,
+            end: start + consumed.trim_end().len(),
+        }
+    }
+    /// Moves both ends of the span.
+    pub fn map(self, f: impl Fn(usize) -> usize) -> Span {
+        Span {
+            start: f(self.start),
+            end: f(self.end),
+        }
+    }
+    /// The position where the span starts.
+    pub fn position(&self, source: &str) -> Position {
+        Position::new(source, self.start)
+    }
+}
+
+/// A line and column in the source text, both starting on 1.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+impl Position {
+    /// Finds the position of a byte offset.
+    pub fn new(source: &str, offset: usize) -> Position {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+        Position {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// A node of the document along with its location in the source.
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+/// Runs a parser, pairing its output with the span of `source` it consumed.
+pub fn spanned<'a, P, O>(source: &'a str, parser: P) 
+-> impl Fn(&'a str) -> IResult<&'a str, Spanned<O>>
+    where P: Fn(&'a str) -> IResult<&'a str, O>
+{
+    move |input: &'a str| {
+        let (rest, node) = parser(input)?;
+        let span = Span::consumed(source, input, rest);
+
+        Ok((rest, Spanned { node, span }))
+    }
+}
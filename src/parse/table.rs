@@ -0,0 +1,111 @@
+use super::{parse_inlines, format_text, split_label, eof, Block, Inline};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{verify, map_opt, opt},
+    multi::many0,
+    sequence::{pair, preceded, terminated},
+    character::complete::{line_ending, not_line_ending},
+};
+use super::IResult;
+
+/// How the text of a table column is aligned.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Alignment {
+    /// No alignment was given, which is usually left.
+    Default,
+    Left,
+    Center,
+    Right,
+}
+
+/// The cells of a table row.
+pub type Row = Vec<Vec<Inline>>;
+
+/// A line with at least one pipe, without the line ending.
+fn row_line(input: &str) -> IResult<&str, &str> {
+    terminated(
+        verify(not_line_ending, |l: &str| l.contains('|') && !l.trim().is_empty()),
+        alt((line_ending, eof))
+    )(input)
+}
+
+// splits a row into its cells, pipes can be escaped as `\|`
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(l) if !l.ends_with('\\') => l,
+        _ => line,
+    };
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    cells.into_iter().map(|c| c.trim().to_string()).collect()
+}
+
+// the row below the header, such as `| :--- | :---: | ---: |`
+fn alignments(line: &str) -> Option<Vec<Alignment>> {
+    split_row(line).iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (true, false) => Alignment::Left,
+                (false, true) => Alignment::Right,
+                (false, false) => Alignment::Default,
+            })
+        })
+        .collect()
+}
+
+fn cells(line: &str, columns: usize) -> Row {
+    let mut cells: Row = split_row(line).iter()
+        .map(|c| parse_inlines(c))
+        .collect();
+    // rows are made to fit the header
+    cells.resize(columns, Vec::new());
+    cells
+}
+
+/// Parses a pipe table: a header row, a row with the alignment of each
+/// column, the rest of the rows and an optional `Table: caption {#label}`.
+pub fn parse_table(input: &str) -> IResult<&str, Block> {
+    let (rest, (header, align)) = verify(
+        pair(row_line, map_opt(row_line, alignments)),
+        |(header, align): &(&str, Vec<Alignment>)| split_row(header).len() == align.len()
+    )(input)?;
+    let (rest, rows) = many0(row_line)(rest)?;
+    let (rest, caption) = opt(terminated(
+        preceded(tag("Table:"), not_line_ending),
+        alt((line_ending, eof))
+    ))(rest)?;
+    let (rest, _) = many0(line_ending)(rest)?;
+
+    let columns = align.len();
+    let (caption, label) = split_label(caption.unwrap_or(""));
+
+    Ok((rest, Block::Table {
+        header: cells(header, columns),
+        rows: rows.iter().map(|r| cells(r, columns)).collect(),
+        align,
+        caption: parse_inlines(&format_text(caption.trim())),
+        label,
+    }))
+}
@@ -1,11 +1,11 @@
 use nom::{
     branch::alt,
     bytes::complete::{tag, take, take_while1},
-    combinator::{verify, map},
+    combinator::{verify, map, map_res, map_opt},
     sequence::{delimited, terminated},
-    IResult
 };
-use numerals::roman::Numeral;
+use super::IResult;
+use numerals::roman::{Numeral, Roman};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ListToken {
@@ -49,7 +49,72 @@ impl TokenEnumerator {
         };
         format!("{}*", s)
     }
+    /// Formats the `n`th value of the enumerator, starting on 1.
+    pub fn format(&self, n: usize) -> String {
+        use TokenEnumerator::*;
+
+        match self {
+            Numerical       => n.to_string(),
+            Alphabetical(u) => alphabetical(n, *u),
+            Roman(u)        => roman(n, *u),
+        }
+    }
+    /// The value of the `type` attribute of an HTML `<ol>`.
+    pub fn html_type(&self) -> &'static str {
+        use TokenEnumerator::*;
+
+        match self {
+            Numerical           => "1",
+            Alphabetical(true)  => "A",
+            Alphabetical(false) => "a",
+            Roman(true)         => "I",
+            Roman(false)        => "i",
+        }
+    }
+    /// The CSS counter style equivalent to the enumerator.
+    pub fn css(&self) -> &'static str {
+        use TokenEnumerator::*;
+
+        match self {
+            Numerical           => "decimal",
+            Alphabetical(true)  => "upper-alpha",
+            Alphabetical(false) => "lower-alpha",
+            Roman(true)         => "upper-roman",
+            Roman(false)        => "lower-roman",
+        }
+    }
+}
+// bijective base-26, so after `z` come `aa`, `ab` and so on
+fn alphabetical(mut n: usize, uppercase: bool) -> String {
+    if n == 0 {
+        return n.to_string();
+    }
+    let first = if uppercase { b'A' } else { b'a' };
+    let mut letters = Vec::new();
+
+    while n > 0 {
+        n -= 1;
+        letters.push((first + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    letters.iter().rev().collect()
 }
+
+// roman numerals can't be zero and don't go past 3999 
+// (MMMCMXCIX), so those are written in arabic numerals
+fn roman(n: usize, uppercase: bool) -> String {
+    if n == 0 || n > 3999 {
+        return n.to_string();
+    }
+    let roman = Roman::from(n as i16);
+
+    if uppercase {
+        format!("{:X}", roman)
+    } else {
+        format!("{:x}", roman)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct TokenWrapper(String);
 
@@ -60,6 +125,13 @@ impl TokenWrapper {
     pub fn unnumbered(&self) -> String {
         self.0.clone()
     }
+    /// Maps the literal text around the enumerator placeholder.
+    pub fn map_text<F>(&self, f: F) -> TokenWrapper 
+        where F: Fn(&str) -> String
+    {
+        let parts: Vec<String> = self.0.split("{}").map(f).collect();
+        TokenWrapper(parts.join("{}"))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -96,32 +168,49 @@ fn parse_unnumbered_token(input: &str) -> IResult<&str, ListToken> {
         hyphen
     ))(input)
 }
-fn parse_enumerator(input: &str) -> IResult<&str, TokenEnumerator> {
+// alphabetical value of a letter, `a` being 1
+fn letter_value(c: char) -> usize {
+    (c.to_ascii_lowercase() as usize) - ('a' as usize) + 1
+}
+
+// the enumerator along with the value that it represents
+fn parse_enumerator(input: &str) -> IResult<&str, (TokenEnumerator, usize)> {
     let numerical =
-        map(
-            take_while1(|c: char| c.is_digit(10)),
-            |_| TokenEnumerator::Numerical
+        map_res(
+            take_while1(|c: char| c.is_ascii_digit()),
+            |n: &str| n.parse().map(|v| (TokenEnumerator::Numerical, v))
         );
     let alphabetic = 
         map(
             verify(
                 take(1u8),
                 |c: &str| {
-                    let c = c.chars().nth(0).unwrap();
-                    c.is_alphabetic()
+                    let c = c.chars().next().unwrap();
+                    c.is_ascii_alphabetic()
                 }
             ),
             |a: &str| {
-                let ch = a.chars().nth(0).unwrap();
-                TokenEnumerator::Alphabetical(ch.is_uppercase())
+                let ch = a.chars().next().unwrap();
+                (TokenEnumerator::Alphabetical(ch.is_uppercase()), letter_value(ch))
             }
         );
     let roman =
-        map(
-            take_while1(|r| Numeral::from_char(r).is_some()),
+        map_opt(
+            verify(
+                take_while1(|r| Numeral::from_char(r).is_some()),
+                // single letters such as `c` or `d` are most likely
+                // starting an alphabetical list, `i` is the exception
+                |n: &str| n.len() > 1 || n.eq_ignore_ascii_case("i")
+            ),
             |n: &str| {
-                let ch = n.chars().nth(0).unwrap();
-                TokenEnumerator::Roman(ch.is_uppercase())
+                let ch = n.chars().next().unwrap();
+                let value = Roman::parse(n)?.value_checked()?;
+
+                if value > 0 {
+                    Some((TokenEnumerator::Roman(ch.is_uppercase()), value as usize))
+                } else {
+                    None
+                }
             }
         );
     alt((
@@ -131,7 +220,7 @@ fn parse_enumerator(input: &str) -> IResult<&str, TokenEnumerator> {
     ))(input)
 }
 
-fn parse_enumerated_token(input: &str) -> IResult<&str, ListToken> {
+fn parse_enumerated_token(input: &str) -> IResult<&str, (ListToken, usize)> {
     // TODO move all of these to their own functions
     let symbol_terminated = |symbol: &'static str|
         map(
@@ -163,17 +252,18 @@ fn parse_enumerated_token(input: &str) -> IResult<&str, ListToken> {
             parenthesis_terminated,
             parenthesis_delimited,
         )),
-        |(wrapper, enumerator)| 
-            ListToken { 
+        |(wrapper, (enumerator, value))| 
+            (ListToken { 
                 wrapper: TokenWrapper(wrapper), 
                 enumerator: Some(enumerator) 
-            }
+            }, value)
     )(input)
 }
-// this parses the full token.
-pub fn parse_item_token(input: &str) -> IResult<&str, ListToken> {
+// this parses the full token, along with the value of its
+// enumerator (always 1 when unnumbered).
+pub fn parse_item_token(input: &str) -> IResult<&str, (ListToken, usize)> {
     alt((
-        parse_unnumbered_token,
+        map(parse_unnumbered_token, |token| (token, 1)),
         parse_enumerated_token,
     ))(input)
 }
@@ -1,5 +1,6 @@
 use nom::{
     IResult,
+    error::{ErrorKind, ParseError},
     combinator::{not, recognize},
     multi::many1_count,
     sequence::preceded,
@@ -37,3 +38,12 @@ pub fn take_until_match<'a, P, O, E: nom::error::ParseError<&'a str>>(parser: P)
             )
         )(input)
 }
+
+/// Succeeds only at the end of the input
+pub fn eof<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+    if input.is_empty() {
+        Ok((input, input))
+    } else {
+        Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Eof)))
+    }
+}
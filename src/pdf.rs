@@ -1,28 +1,114 @@
 use crate::parse::*;
+#[cfg(any(feature = "pdf", feature = "latex-engine"))]
 use crate::compiler::*;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
+#[cfg(feature = "pdf")]
 use tectonic::{Error as TectonicError, latex_to_pdf};
 use thiserror::Error;
+#[cfg(feature = "latex-engine")]
+use crate::engine::{Engine, EngineError};
+#[cfg(feature = "native-pdf")]
+use crate::render::{Fonts, RenderError, render_pdf};
 
 #[derive(Error, Debug)]
 pub enum PdfError {
     #[error("File writing error {0}")]
     IoError(#[from] io::Error),
+    #[cfg(feature = "pdf")]
     #[error("Tectonic compilation error: {0}")]
     TectonicError(#[from] TectonicError),
     #[error("Output file {0} already exists")]
-    FileExists(PathBuf)
+    FileExists(PathBuf),
+    #[error("Image file {0} doesn't exist")]
+    MissingFile(PathBuf),
+    #[error("Path {0} can't be read by LaTeX, it has `%`, `#`, `{{` or `}}`")]
+    UnreadablePath(PathBuf),
+    #[cfg(feature = "latex-engine")]
+    #[error("{0}")]
+    EngineError(#[from] EngineError),
+    #[cfg(feature = "native-pdf")]
+    #[error("{0}")]
+    RenderError(#[from] RenderError),
 }
 
-/// Compiles a document to binary PDF data via Tectonic.
-pub fn compile_to_pdf(document: &Document) -> Result<Vec<u8>, TectonicError> {
-    let compiled = IntoLatex.compile(&document);
-    Ok(latex_to_pdf(compiled)?)
+/// How a document is turned into a PDF.
+#[derive(Debug, Clone)]
+pub enum Backend {
+    /// Compiled to LaTeX, which Tectonic compiles to PDF.
+    #[cfg(feature = "pdf")]
+    Tectonic(LatexConfig),
+    /// Compiled to LaTeX, which an engine installed in the system 
+    /// compiles to PDF.
+    #[cfg(feature = "latex-engine")]
+    Engine(Engine, LatexConfig),
+    /// Laid out directly with the given fonts, without LaTeX.
+    #[cfg(feature = "native-pdf")]
+    Native(Fonts),
 }
-/// Exports a document to a PDF file via Tectonic.
-pub fn save_as_pdf<T>(document: &Document, out: T, overwrite: bool) -> Result<(), PdfError> 
+#[cfg(feature = "pdf")]
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Tectonic(LatexConfig::default())
+    }
+}
+
+// LaTeX looks for files in the working directory, so the paths of the
+// figures are made relative to it instead of to the document
+#[cfg(any(feature = "pdf", feature = "latex-engine"))]
+fn resolve_paths(blocks: &mut [Spanned<Block>], root: &Path) -> Result<(), PdfError> {
+    for block in blocks {
+        match &mut block.node {
+            Block::Figure { path, .. } => {
+                let resolved = root.join(&path);
+
+                if !resolved.is_file() {
+                    return Err(PdfError::MissingFile(resolved));
+                }
+                if resolved.to_string_lossy().contains(|c| "%#{}".contains(c)) {
+                    return Err(PdfError::UnreadablePath(resolved));
+                }
+                *path = resolved.to_string_lossy().replace('\\', "/");
+            }
+            Block::List(list) => for item in &mut list.vec {
+                resolve_paths(&mut item.blocks, root)?;
+            },
+            Block::Quote(blocks) => resolve_paths(blocks, root)?,
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+// LaTeX code for a document, with the paths of its figures resolved
+#[cfg(any(feature = "pdf", feature = "latex-engine"))]
+fn compile_to_latex(document: &Document, config: &LatexConfig, root: &Path) -> Result<String, PdfError> {
+    let mut document = document.clone();
+    resolve_paths(&mut document.blocks, root)?;
+    Ok(IntoLatex::new(config.clone()).compile(&document))
+}
+
+/// Compiles a document to binary PDF data with the given backend. Files 
+/// referenced by the document are looked up relative to `root`.
+pub fn compile_to_pdf(document: &Document, backend: &Backend, root: &Path) -> Result<Vec<u8>, PdfError> {
+    // images aren't drawn natively, so their files aren't needed
+    #[cfg(not(any(feature = "pdf", feature = "latex-engine")))]
+    let _ = root;
+
+    match backend {
+        #[cfg(feature = "pdf")]
+        Backend::Tectonic(config) => Ok(latex_to_pdf(compile_to_latex(document, config, root)?)?),
+        #[cfg(feature = "latex-engine")]
+        Backend::Engine(engine, config) => Ok(engine.compile(&compile_to_latex(document, config, root)?)?),
+        #[cfg(feature = "native-pdf")]
+        Backend::Native(fonts) => Ok(render_pdf(document, fonts)?),
+    }
+}
+/// Exports a document to a PDF file with the given backend. Files 
+/// referenced by the document are looked up relative to `root`, usually 
+/// the directory of the document.
+pub fn save_as_pdf<T>(document: &Document, backend: &Backend, root: &Path, out: T, overwrite: bool) -> Result<(), PdfError> 
 where T: Into<PathBuf> 
 {
     let out = out.into(); // pdf file output
@@ -31,7 +117,7 @@ where T: Into<PathBuf>
         return Err(PdfError::FileExists(out));
     }
 
-    let pdf = compile_to_pdf(&document)?;
+    let pdf = compile_to_pdf(document, backend, root)?;
     fs::write(&out, pdf)?;
     Ok(())
 }
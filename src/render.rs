@@ -0,0 +1,595 @@
+use crate::parse::{Document, Block, Inline, List, Alignment, Row, Numbering, OutlineEntry};
+
+use owned_ttf_parser::{AsFaceRef, OwnedFace};
+use printpdf::{ExternalFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point, Pt};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RenderError {
+    #[error("Error reading font: {0}")]
+    IoError(#[from] io::Error),
+    #[error("Font {0} is not a TrueType or OpenType font")]
+    InvalidFont(PathBuf),
+    #[error("No font file starts with {0}")]
+    MissingFont(PathBuf),
+    #[error("No fonts were found in the system, they have to be given")]
+    NoSystemFonts,
+    #[error("PDF writing error: {0}")]
+    PdfError(#[from] printpdf::Error),
+}
+
+/// The fonts a document is rendered with, embedded into the PDF.
+#[derive(Debug, Clone)]
+pub struct Fonts {
+    // in the order of `Style`
+    data: [Vec<u8>; 5],
+}
+
+// the families looked for when no fonts are given, with their monospace fonts
+const SYSTEM_FAMILIES: [(&str, &str); 5] = [
+    ("/usr/share/fonts/truetype/dejavu/DejaVuSerif", "/usr/share/fonts/truetype/dejavu/DejaVuSansMono"),
+    ("/usr/share/fonts/TTF/DejaVuSerif", "/usr/share/fonts/TTF/DejaVuSansMono"),
+    ("/usr/share/fonts/dejavu-serif-fonts/DejaVuSerif", "/usr/share/fonts/dejavu-sans-mono-fonts/DejaVuSansMono"),
+    ("/usr/share/fonts/truetype/liberation/LiberationSerif", "/usr/share/fonts/truetype/liberation/LiberationMono"),
+    ("/usr/share/fonts/liberation-serif/LiberationSerif", "/usr/share/fonts/liberation-mono/LiberationMono"),
+];
+
+impl Fonts {
+    // the first of the files named `prefix` plus one of the suffixes
+    fn find(prefix: &Path, suffixes: &[&str]) -> Result<Vec<u8>, RenderError> {
+        for suffix in suffixes {
+            for extension in &["ttf", "otf"] {
+                let mut path = prefix.as_os_str().to_owned();
+                path.push(format!("{}.{}", suffix, extension));
+                let path = PathBuf::from(path);
+
+                if path.is_file() {
+                    let data = fs::read(&path)?;
+
+                    // loaded the same way as when it's embedded
+                    if ExternalFont::new(data.as_slice(), 0).is_err() {
+                        return Err(RenderError::InvalidFont(path));
+                    }
+                    return Ok(data);
+                }
+            }
+        }
+        Err(RenderError::MissingFont(prefix.to_path_buf()))
+    }
+    /// Loads a family from the files starting with `prefix`, such as
+    /// `fonts/DejaVuSerif` for `fonts/DejaVuSerif.ttf`,
+    /// `fonts/DejaVuSerif-Bold.ttf`, `fonts/DejaVuSerif-Italic.ttf` and
+    /// `fonts/DejaVuSerif-BoldItalic.ttf`. Code is set with the regular
+    /// font until `with_monospace` is used.
+    pub fn family<P: AsRef<Path>>(prefix: P) -> Result<Fonts, RenderError> {
+        let prefix = prefix.as_ref();
+        let regular = Fonts::find(prefix, &["", "-Regular"])?;
+
+        Ok(Fonts {
+            data: [
+                regular.clone(),
+                Fonts::find(prefix, &["-Bold"])?,
+                Fonts::find(prefix, &["-Italic", "-Oblique"])?,
+                Fonts::find(prefix, &["-BoldItalic", "-BoldOblique"])?,
+                regular,
+            ],
+        })
+    }
+    /// Sets code with the regular font of the family starting with `prefix`.
+    pub fn with_monospace<P: AsRef<Path>>(mut self, prefix: P) -> Result<Fonts, RenderError> {
+        self.data[Style::Monospace as usize] = Fonts::find(prefix.as_ref(), &["", "-Regular"])?;
+        Ok(self)
+    }
+    /// Looks for a serif and a monospace family installed in the system.
+    pub fn system() -> Result<Fonts, RenderError> {
+        SYSTEM_FAMILIES.iter()
+            .find_map(|(family, monospace)| Fonts::family(family)
+                .and_then(|fonts| fonts.with_monospace(monospace))
+                .ok())
+            .ok_or(RenderError::NoSystemFonts)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Style {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Monospace,
+}
+impl Style {
+    fn bold(self) -> Style {
+        match self {
+            Style::Italic | Style::BoldItalic => Style::BoldItalic,
+            Style::Monospace => Style::Monospace,
+            _ => Style::Bold,
+        }
+    }
+    fn italic(self) -> Style {
+        match self {
+            Style::Bold | Style::BoldItalic => Style::BoldItalic,
+            Style::Monospace => Style::Monospace,
+            _ => Style::Italic,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Align {
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+// text set in a single font
+#[derive(Debug, Clone)]
+struct Piece {
+    text: String,
+    style: Style,
+    size: f32,
+    // raised, for footnote marks
+    rise: f32,
+}
+// what lines are broken between
+#[derive(Debug, Clone, Default)]
+struct Word {
+    pieces: Vec<Piece>,
+    width: f32,
+}
+
+// A4, in points
+const PAGE_WIDTH: f32 = 595.28;
+const PAGE_HEIGHT: f32 = 841.89;
+const MARGIN: f32 = 72.0;
+const TEXT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+const BODY_SIZE: f32 = 11.0;
+const CODE_SIZE: f32 = 9.0;
+const NOTE_SIZE: f32 = 9.0;
+const LEADING: f32 = 1.3;
+// the indentation of lists and quotes
+const INDENT: f32 = 20.0;
+
+struct Renderer<'a> {
+    pdf: PdfDocumentReference,
+    layer: PdfLayerReference,
+    data: &'a Fonts,
+    // only the fonts that are used are embedded
+    fonts: [Option<IndirectFontRef>; 5],
+    faces: Vec<OwnedFace>,
+    // distance from the top of the page to the next line
+    y: f32,
+    pages: usize,
+    numbering: Numbering,
+    outline: Vec<OutlineEntry>,
+    // the token of a list item, put beside its first line
+    label: Option<(Vec<Word>, f32)>,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(title: &str, fonts: &'a Fonts) -> Renderer<'a> {
+        let (pdf, page, layer) = PdfDocument::new(title, Mm::from(Pt(PAGE_WIDTH)), Mm::from(Pt(PAGE_HEIGHT)), "Text");
+        let layer = pdf.get_page(page).get_layer(layer);
+        let mut renderer = Renderer {
+            pdf,
+            layer,
+            data: fonts,
+            fonts: Default::default(),
+            faces: Vec::new(),
+            y: MARGIN,
+            pages: 1,
+            numbering: Numbering::default(),
+            outline: Vec::new(),
+            label: None,
+        };
+        for data in &fonts.data {
+            // checked when the fonts were loaded
+            renderer.faces.push(OwnedFace::from_vec(data.clone(), 0).expect("font should be valid"));
+        }
+        renderer.footer();
+        renderer
+    }
+    fn font(&mut self, style: Style) -> IndirectFontRef {
+        let data = &self.data.data[style as usize];
+        let pdf = &self.pdf;
+
+        self.fonts[style as usize]
+            // checked when the fonts were loaded
+            .get_or_insert_with(|| pdf.add_external_font(data.as_slice()).expect("font should be valid"))
+            .clone()
+    }
+    fn footer(&mut self) {
+        let number = vec![self.word(&self.pages.to_string(), Style::Regular, NOTE_SIZE)];
+        self.draw_line(&number, MARGIN, TEXT_WIDTH, Align::Center, PAGE_HEIGHT - MARGIN / 2.0, true);
+    }
+    // moves down by `height`, going to the next page if it doesn't fit
+    fn advance(&mut self, height: f32) -> f32 {
+        if self.y + height > PAGE_HEIGHT - MARGIN && self.y > MARGIN {
+            let (page, layer) = self.pdf.add_page(Mm::from(Pt(PAGE_WIDTH)), Mm::from(Pt(PAGE_HEIGHT)), "Text");
+            self.layer = self.pdf.get_page(page).get_layer(layer);
+            self.y = MARGIN;
+            self.pages += 1;
+            self.footer();
+        }
+        let top = self.y;
+        self.y += height;
+        top
+    }
+    // goes to the next page if `height` doesn't fit, without moving
+    fn reserve(&mut self, height: f32) {
+        self.y = self.advance(height);
+    }
+    fn space(&mut self, height: f32) {
+        // space isn't carried over to the next page
+        self.y = (self.y + height).min(PAGE_HEIGHT - MARGIN);
+    }
+
+    fn width(&self, text: &str, style: Style, size: f32) -> f32 {
+        let face = self.faces[style as usize].as_face_ref();
+        let units: u32 = text.chars()
+            .filter_map(|c| face.glyph_index(c))
+            .filter_map(|glyph| face.glyph_hor_advance(glyph))
+            .map(u32::from)
+            .sum();
+        units as f32 * size / face.units_per_em() as f32
+    }
+    fn word(&self, text: &str, style: Style, size: f32) -> Word {
+        let mut word = Word::default();
+        self.push_piece(&mut word, text, style, size, 0.0);
+        word
+    }
+    fn push_piece(&self, word: &mut Word, text: &str, style: Style, size: f32, rise: f32) {
+        word.width += self.width(text, style, size);
+        word.pieces.push(Piece { text: text.to_string(), style, size, rise });
+    }
+    // splits the text into words, a word may have several styles
+    fn push_words(&self, words: &mut Vec<Word>, inlines: &[Inline], style: Style, size: f32) {
+        let push_text = |words: &mut Vec<Word>, text: &str, style: Style| {
+            for (i, part) in text.split(char::is_whitespace).enumerate() {
+                // whitespace starts a new word
+                if i > 0 {
+                    words.push(Word::default());
+                }
+                if !part.is_empty() {
+                    let last = words.last_mut().expect("words start with an empty one");
+                    self.push_piece(last, part, style, size, 0.0);
+                }
+            }
+        };
+
+        for inline in inlines {
+            match inline {
+                Inline::Text(t) => push_text(words, t, style),
+                Inline::Emphasis(i) => self.push_words(words, i, style.italic(), size),
+                Inline::Strong(i) => self.push_words(words, i, style.bold(), size),
+                Inline::Code(c) | Inline::Math(c) | Inline::Url(c) => push_text(words, c, Style::Monospace),
+                Inline::Link(text, _) => self.push_words(words, text, style, size),
+                Inline::FootnoteReference(id) => push_text(words, &format!("[^{}]", id), style),
+                Inline::Footnote(number, _) => {
+                    let last = words.last_mut().expect("words start with an empty one");
+                    self.push_piece(last, &number.to_string(), Style::Regular, size * 0.7, size * 0.35);
+                }
+                Inline::Reference(label, number) => match number {
+                    Some(number) => push_text(words, number, style),
+                    None => push_text(words, &format!("@{}", label), style),
+                },
+                Inline::Raw(..) => (),
+            }
+        }
+    }
+    fn words(&self, inlines: &[Inline], style: Style, size: f32) -> Vec<Word> {
+        let mut words = vec![Word::default()];
+        self.push_words(&mut words, inlines, style, size);
+        words.retain(|word| !word.pieces.is_empty());
+        words
+    }
+    // greedy line breaking, words wider than the line get one of their own
+    fn break_lines(&self, words: Vec<Word>, width: f32, size: f32) -> Vec<Vec<Word>> {
+        let space = self.width(" ", Style::Regular, size);
+        let mut lines: Vec<Vec<Word>> = Vec::new();
+        let mut used = 0.0;
+
+        for word in words {
+            match lines.last_mut() {
+                Some(line) if used + space + word.width <= width => {
+                    used += space + word.width;
+                    line.push(word);
+                }
+                _ => {
+                    used = word.width;
+                    lines.push(vec![word]);
+                }
+            }
+        }
+        lines
+    }
+
+    fn draw_line(&mut self, words: &[Word], left: f32, width: f32, align: Align, baseline: f32, last: bool) {
+        let size = words.iter()
+            .flat_map(|word| &word.pieces)
+            .map(|piece| piece.size)
+            .fold(0.0, f32::max);
+        let space = self.width(" ", Style::Regular, size);
+        let used: f32 = words.iter().map(|word| word.width).sum::<f32>()
+            + space * words.len().saturating_sub(1) as f32;
+
+        let (mut x, gap) = match align {
+            Align::Left => (left, space),
+            Align::Center => (left + (width - used) / 2.0, space),
+            Align::Right => (left + width - used, space),
+            // the last line of a paragraph isn't stretched
+            Align::Justify if last || words.len() < 2 => (left, space),
+            Align::Justify => (left, space + (width - used) / (words.len() - 1) as f32),
+        };
+        for word in words {
+            for piece in &word.pieces {
+                let font = self.font(piece.style);
+                self.layer.use_text(
+                    piece.text.as_str(),
+                    piece.size,
+                    Mm::from(Pt(x)),
+                    Mm::from(Pt(PAGE_HEIGHT - baseline + piece.rise)),
+                    &font,
+                );
+                x += self.width(&piece.text, piece.style, piece.size);
+            }
+            x += gap;
+        }
+    }
+    // the lines of a block, with the pending list token beside the first one
+    fn lines(&mut self, lines: &[Vec<Word>], size: f32, left: f32, width: f32, align: Align) {
+        for (i, line) in lines.iter().enumerate() {
+            let baseline = self.advance(size * LEADING) + size;
+
+            if let Some((label, right)) = self.label.take() {
+                let label_width: f32 = label.iter().map(|word| word.width).sum();
+                self.draw_line(&label, right - label_width, label_width, Align::Left, baseline, true);
+            }
+            self.draw_line(line, left, width, align, baseline, i + 1 == lines.len());
+        }
+    }
+    fn text(&mut self, inlines: &[Inline], style: Style, size: f32, left: f32, width: f32, align: Align) {
+        let words = self.words(inlines, style, size);
+        let lines = self.break_lines(words, width, size);
+        self.lines(&lines, size, left, width, align);
+    }
+    fn plain(&mut self, text: &str, style: Style, size: f32, left: f32, width: f32, align: Align) {
+        self.text(&[Inline::Text(text.to_string())], style, size, left, width, align);
+    }
+    fn rule(&mut self, left: f32, width: f32) {
+        let y = PAGE_HEIGHT - self.advance(0.0);
+        let point = |x: f32| (Point::new(Mm::from(Pt(x)), Mm::from(Pt(y))), false);
+
+        self.layer.set_outline_thickness(0.5);
+        self.layer.add_line(Line {
+            points: vec![point(left), point(left + width)],
+            is_closed: false,
+        });
+    }
+
+    fn title(&mut self, document: &Document) {
+        let metadata = &document.metadata;
+
+        if let Some(title) = &metadata.title {
+            self.plain(title, Style::Bold, 20.0, MARGIN, TEXT_WIDTH, Align::Center);
+            self.space(8.0);
+
+            for value in [&metadata.author, &metadata.date].iter().copied().flatten() {
+                self.plain(value, Style::Regular, 12.0, MARGIN, TEXT_WIDTH, Align::Center);
+            }
+            self.space(16.0);
+        }
+        if let Some(a) = &metadata.r#abstract {
+            self.plain("Abstract", Style::Bold, 10.0, MARGIN, TEXT_WIDTH, Align::Center);
+            self.space(4.0);
+            self.plain(a, Style::Regular, 10.0, MARGIN + 2.0 * INDENT, TEXT_WIDTH - 4.0 * INDENT, Align::Justify);
+            self.space(16.0);
+        }
+        if metadata.toc {
+            self.contents();
+        }
+    }
+    fn contents(&mut self) {
+        self.plain("Contents", Style::Bold, 15.0, MARGIN, TEXT_WIDTH, Align::Left);
+        self.space(6.0);
+
+        let outline = self.outline.clone();
+        self.outline_entries(&outline, 0);
+        self.space(12.0);
+    }
+    fn outline_entries(&mut self, entries: &[OutlineEntry], depth: usize) {
+        let left = MARGIN + INDENT * depth as f32;
+
+        for entry in entries {
+            let mut words = vec![self.word(&entry.number, Style::Regular, BODY_SIZE)];
+            words.extend(self.words(&entry.title, Style::Regular, BODY_SIZE));
+            let lines = self.break_lines(words, TEXT_WIDTH - INDENT * depth as f32, BODY_SIZE);
+
+            self.lines(&lines, BODY_SIZE, left, TEXT_WIDTH, Align::Left);
+            self.outline_entries(&entry.children, depth + 1);
+        }
+    }
+    // puts the pending list token on a line of its own
+    fn flush_label(&mut self, left: f32, width: f32) {
+        if self.label.is_some() {
+            self.lines(&[Vec::new()], BODY_SIZE, left, width, Align::Left);
+        }
+    }
+    fn list(&mut self, list: &List, left: f32, width: f32) {
+        // a sublist right at the start of an item
+        self.flush_label(left, width);
+
+        for (index, item) in list.vec.iter().enumerate() {
+            let token = match &list.token.enumerator {
+                None => list.token.wrapper.unnumbered(),
+                Some(e) => list.token.wrapper.label(&e.format(list.start + index)),
+            };
+            self.label = Some((vec![self.word(&token, Style::Regular, BODY_SIZE)], left + INDENT - 5.0));
+
+            for block in &item.blocks {
+                self.block(block, left + INDENT, width - INDENT);
+            }
+            // an empty item still shows its token
+            self.flush_label(left + INDENT, width - INDENT);
+        }
+    }
+    fn table(&mut self, header: &Row, align: &[Alignment], rows: &[Row], left: f32, width: f32) {
+        let columns = header.len().max(1);
+        let column = width / columns as f32;
+        let padding = 4.0;
+
+        self.rule(left, width);
+        self.space(2.0);
+
+        for (r, row) in std::iter::once(header).chain(rows).enumerate() {
+            let style = if r == 0 { Style::Bold } else { Style::Regular };
+            let cells: Vec<Vec<Vec<Word>>> = row.iter()
+                .map(|cell| {
+                    let words = self.words(cell, style, BODY_SIZE);
+                    self.break_lines(words, column - 2.0 * padding, BODY_SIZE)
+                })
+                .collect();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(0);
+
+            for i in 0..height {
+                let baseline = self.advance(BODY_SIZE * LEADING) + BODY_SIZE;
+
+                for (c, lines) in cells.iter().enumerate() {
+                    let align = match align.get(c) {
+                        Some(Alignment::Center) => Align::Center,
+                        Some(Alignment::Right) => Align::Right,
+                        _ => Align::Left,
+                    };
+                    if let Some(line) = lines.get(i) {
+                        let x = left + column * c as f32 + padding;
+                        self.draw_line(line, x, column - 2.0 * padding, align, baseline, true);
+                    }
+                }
+            }
+            if r == 0 {
+                self.space(2.0);
+                self.rule(left, width);
+                self.space(2.0);
+            }
+        }
+        self.space(2.0);
+        self.rule(left, width);
+    }
+    // the caption of a figure or table, if it's numbered
+    fn caption(&mut self, kind: &str, number: Option<String>, caption: &[Inline], left: f32, width: f32) {
+        if let Some(number) = number {
+            let name = match caption.is_empty() {
+                true => format!("{} {}", kind, number),
+                false => format!("{} {}:", kind, number),
+            };
+            let mut words = vec![self.word(&name, Style::Bold, 10.0)];
+            words.extend(self.words(caption, Style::Regular, 10.0));
+            let lines = self.break_lines(words, width, 10.0);
+            self.space(4.0);
+            self.lines(&lines, 10.0, left, width, Align::Center);
+        }
+    }
+
+    fn block(&mut self, block: &Block, left: f32, width: f32) {
+        // numbered the same as the references to them
+        let number = self.numbering.next(block);
+
+        match block {
+            Block::Heading(level, title, _) => {
+                let size = match level {
+                    1 => 16.0,
+                    2 => 13.0,
+                    _ => BODY_SIZE,
+                };
+                let mut words = Vec::new();
+
+                if let Some(number) = number {
+                    words.push(self.word(&number, Style::Bold, size));
+                }
+                words.extend(self.words(title, Style::Bold, size));
+                let lines = self.break_lines(words, width, size);
+
+                self.space(size);
+                // keeps the heading with the line after it
+                self.reserve((lines.len() as f32 + 1.0) * size * LEADING);
+                self.lines(&lines, size, left, width, Align::Left);
+                self.space(size / 2.0);
+            }
+            Block::Paragraph(p) => {
+                self.text(p, Style::Regular, BODY_SIZE, left, width, Align::Justify);
+                self.space(BODY_SIZE / 2.0);
+            }
+            Block::List(list) => {
+                self.list(list, left, width);
+                self.space(BODY_SIZE / 2.0);
+            }
+            Block::CodeBlock { code, .. } => {
+                let lines: Vec<Vec<Word>> = code.lines()
+                    .map(|line| vec![self.word(line, Style::Monospace, CODE_SIZE)])
+                    .collect();
+                self.lines(&lines, CODE_SIZE, left + INDENT / 2.0, width, Align::Left);
+                self.space(BODY_SIZE / 2.0);
+            }
+            Block::Math(m) => {
+                let lines: Vec<Vec<Word>> = m.lines()
+                    .map(|line| vec![self.word(line.trim(), Style::Monospace, BODY_SIZE)])
+                    .collect();
+                self.lines(&lines, BODY_SIZE, left, width, Align::Center);
+                self.space(BODY_SIZE / 2.0);
+            }
+            Block::Quote(blocks) => {
+                for block in blocks {
+                    self.block(block, left + INDENT, width - 2.0 * INDENT);
+                }
+            }
+            Block::Table { header, align, rows, caption, .. } => {
+                self.table(header, align, rows, left, width);
+                self.caption("Table", number, caption, left, width);
+                self.space(BODY_SIZE);
+            }
+            // images aren't drawn yet, only their place is kept
+            Block::Figure { path, caption, .. } => {
+                self.plain(&format!("[{}]", path), Style::Monospace, CODE_SIZE, left, width, Align::Center);
+                self.caption("Figure", number, caption, left, width);
+                self.space(BODY_SIZE);
+            }
+            Block::FootnoteDefinition(..) | Block::Raw { .. } => (),
+            Block::TableOfContents => self.contents(),
+        }
+    }
+    fn footnotes(&mut self, footnotes: &[Vec<Inline>]) {
+        if footnotes.is_empty() {
+            return;
+        }
+        self.space(BODY_SIZE);
+        self.rule(MARGIN, TEXT_WIDTH / 3.0);
+        self.space(4.0);
+
+        for (i, text) in footnotes.iter().enumerate() {
+            let token = self.word(&format!("{}.", i + 1), Style::Regular, NOTE_SIZE);
+            self.label = Some((vec![token], MARGIN + INDENT - 5.0));
+            self.text(text, Style::Regular, NOTE_SIZE, MARGIN + INDENT, TEXT_WIDTH - INDENT, Align::Justify);
+        }
+    }
+}
+
+/// Lays out a document into PDF data, without going through LaTeX.
+/// Images aren't drawn, only their paths and captions.
+pub fn render_pdf(document: &Document, fonts: &Fonts) -> Result<Vec<u8>, RenderError> {
+    let title = document.metadata.title.as_deref().unwrap_or("");
+    let mut renderer = Renderer::new(title, fonts);
+    renderer.outline = document.outline();
+
+    renderer.title(document);
+
+    for block in &document.blocks {
+        renderer.block(block, MARGIN, TEXT_WIDTH);
+    }
+    renderer.footnotes(&document.footnotes);
+
+    Ok(renderer.pdf.save_to_bytes()?)
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+use std::{fs, io};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TemplateError {
+    #[error("Error reading template: {0}")]
+    IoError(#[from] io::Error),
+    #[error("Unknown placeholder `${name}$` in line {line} of the template")]
+    UnknownPlaceholder { name: String, line: usize },
+    #[error("Unclosed `$` in line {0} of the template (write `$$` for a literal `$`)")]
+    Unclosed(usize),
+    #[error("Template has no `$body$` placeholder")]
+    MissingBody,
+}
+
+/// A LaTeX document with `$name$` placeholders, which are replaced by
+/// the parts of the compiled document:
+///
+/// - `$body$`: the content of the document.
+/// - `$class$`: the document class.
+/// - `$packages$`: the packages the document needs.
+/// - `$language$`: babel, set up for the language of the document.
+/// - `$metadata$`: the title, author and date, and the PDF metadata.
+/// - `$title$`, `$author$`, `$date$`: the escaped values of the
+///   front matter, or nothing.
+///
+/// `$$` is a literal `$`. A line holding only a placeholder that is
+/// empty is left out.
+#[derive(Debug, Clone)]
+pub struct Template {
+    source: String,
+}
+
+const PLACEHOLDERS: [&str; 8] = [
+    "body", "class", "packages", "language", "metadata", "title", "author", "date",
+];
+
+impl Default for Template {
+    fn default() -> Self {
+        Template {
+            source: String::from(include_str!("template.tex")),
+        }
+    }
+}
+impl Template {
+    /// Checks that every placeholder of the template is known.
+    pub fn new(source: &str) -> Result<Template, TemplateError> {
+        let mut body = false;
+
+        expand(source, |name| {
+            body |= name == "body";
+            PLACEHOLDERS.contains(&name).then(String::new)
+        })?;
+
+        if !body {
+            return Err(TemplateError::MissingBody);
+        }
+        Ok(Template {
+            source: String::from(source),
+        })
+    }
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Template, TemplateError> {
+        Template::new(&fs::read_to_string(path)?)
+    }
+    /// Replaces the placeholders with the values given by `value`.
+    pub(crate) fn render<F>(&self, value: F) -> String
+    where F: FnMut(&str) -> String
+    {
+        let mut value = value;
+
+        // the placeholders were checked when the template was made
+        expand(&self.source, |name| Some(value(name)))
+            .expect("template should have been validated")
+    }
+}
+
+fn expand<F>(source: &str, mut value: F) -> Result<String, TemplateError>
+where F: FnMut(&str) -> Option<String>
+{
+    let mut buf = String::new();
+
+    for (n, line) in source.lines().enumerate() {
+        let unknown = |name: &str| TemplateError::UnknownPlaceholder {
+            name: String::from(name),
+            line: n + 1,
+        };
+        let trimmed = line.trim();
+
+        // a placeholder on its own line
+        if let Some(name) = trimmed.strip_prefix('$').and_then(|s| s.strip_suffix('$')) {
+            if !name.is_empty() && !name.contains('$') {
+                let value = value(name).ok_or_else(|| unknown(name))?;
+
+                if !value.is_empty() {
+                    buf.push_str(&line[..line.len() - line.trim_start().len()]);
+                    buf.push_str(&value);
+                    buf.push('\n');
+                }
+                continue;
+            }
+        }
+        let mut rest = line;
+
+        while let Some(start) = rest.find('$') {
+            buf.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+
+            let end = rest.find('$').ok_or(TemplateError::Unclosed(n + 1))?;
+            let name = &rest[..end];
+
+            match name {
+                "" => buf.push('$'),
+                _ => buf.push_str(&value(name).ok_or_else(|| unknown(name))?),
+            }
+            rest = &rest[end + 1..];
+        }
+        buf.push_str(rest);
+        buf.push('\n');
+    }
+    if !source.ends_with('\n') {
+        buf.pop();
+    }
+    Ok(buf)
+}